@@ -1,4 +1,4 @@
-import fastf1, sys, os
+import fastf1, sys, os, json
 
 def get_path(path):
     os.makedirs(os.path.dirname(path), exist_ok=True)
@@ -11,7 +11,10 @@ LAP_STATS = [
     "Sector3Time", 
     "Sector1SessionTime", 
     "Sector2SessionTime", 
-    "Sector3SessionTime"
+    "Sector3SessionTime",
+    "Compound",
+    "TyreLife",
+    "FreshTyre"
 ]
 
 DRIVER_STATS = [
@@ -25,7 +28,10 @@ TELEMETRY_STATS = [
     "X", 
     "Y", 
     "Speed", 
-    "RelativeDistance"
+    "Distance",
+    "RelativeDistance",
+    "Throttle",
+    "Brake"
 ]
 
 USAGE_ERROR = 1
@@ -37,6 +43,7 @@ TELEMETRY_DATA_ERROR = 5
 DRIVER_DATA_FILE = "./data/driver{}_data.json"
 LAP_DATA_FILE = "./data/lap{}_data.json"
 TELEMETRY_DATA_FILE = "./data/telemetry{}_data.json"
+SESSION_DATA_FILE = "./data/session_data.json"
 
 try:
     # Format: python fetch.py <framerate> <year> <country> <driver1> <driver2> 
@@ -56,6 +63,54 @@ except:
     print("Unable to fetch session data")
     exit(SESSION_LOAD_ERROR)
 
+# Session metadata is only used for the header, so it is exported on a best-effort basis.
+if os.path.exists(SESSION_DATA_FILE):
+    os.remove(SESSION_DATA_FILE)
+
+try:
+    try:
+        circuit_name = session.session_info["Meeting"]["Circuit"]["ShortName"]
+    except:
+        circuit_name = session.event["Location"]
+
+    session_data = {
+        "EventName": session.event["EventName"],
+        "Year": year,
+        "SessionName": session.name,
+        "CircuitName": circuit_name,
+        "Date": session.date.strftime("%Y-%m-%d")
+    }
+
+    try:
+        weather = session.weather_data
+        session_data["Weather"] = {
+            "AirTemp": float(weather["AirTemp"].mean()),
+            "TrackTemp": float(weather["TrackTemp"].mean()),
+            "Rainfall": bool(weather["Rainfall"].any())
+        }
+    except:
+        pass
+
+    try:
+        corners = session.get_circuit_info().corners
+        lap_distance = session.laps.pick_fastest().get_car_data().add_distance()["Distance"].max()
+        session_data["Corners"] = [
+            {
+                "Number": int(corner["Number"]),
+                "Letter": str(corner["Letter"]),
+                "Distance": float(corner["Distance"]),
+                "RelativeDistance": float(corner["Distance"] / lap_distance)
+            }
+            for _, corner in corners.iterrows()
+        ]
+    except:
+        pass
+
+    with open(get_path(SESSION_DATA_FILE), "w") as file:
+        json.dump(session_data, file)
+except:
+    print("Unable to export session data")
+
 try:
     driver1_data = session.get_driver(driver1)[DRIVER_STATS]
     driver2_data = session.get_driver(driver2)[DRIVER_STATS]
@@ -8,7 +8,16 @@ pub struct DriverTelemetryData {
     pub y: i32,
     pub session_time: i64,
     pub speed: i32,
-    pub relative_distance: f64
+    pub relative_distance: f64,
+    /// Distance from the start of the lap in metres. Missing in laps fetched
+    /// by older versions.
+    #[serde(default)]
+    pub distance: Option<f64>,
+    /// Throttle in percent. Missing in laps fetched by older versions.
+    #[serde(default)]
+    pub throttle: Option<f32>,
+    #[serde(default)]
+    pub brake: Option<bool>
 }
 
 #[derive(Deserialize, Debug)]
@@ -20,7 +29,17 @@ pub struct LapData {
     pub sector3_time: i32,
     pub sector1_session_time: i64,
     pub sector2_session_time: i64,
-    pub sector3_session_time: i64
+    pub sector3_session_time: i64,
+    /// Tyre compound as named by FastF1, e.g. "SOFT". Missing in laps fetched
+    /// by older versions.
+    #[serde(default)]
+    pub compound: Option<String>,
+    /// Laps driven on the tyre, including this one and laps of earlier sessions.
+    #[serde(default)]
+    pub tyre_life: Option<f64>,
+    /// Whether the tyre was new when it was fitted.
+    #[serde(default)]
+    pub fresh_tyre: Option<bool>
 }
 
 #[derive(Deserialize, Debug)]
@@ -32,6 +51,45 @@ pub struct DriverData {
     pub team_color: [u8; 4]
 }
 
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct WeatherData {
+    /// Mean air temperature during the session in °C.
+    pub air_temp: f64,
+    /// Mean track temperature during the session in °C.
+    pub track_temp: f64,
+    pub rainfall: bool
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct CornerData {
+    pub number: u32,
+    /// Suffix of corners sharing a number, e.g. "a" in turn 9a. Usually empty.
+    pub letter: String,
+    /// Position of the apex along the lap in metres.
+    #[serde(default)]
+    pub distance: Option<f64>,
+    /// Position of the apex along the lap, between 0 and 1.
+    pub relative_distance: f64
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct SessionData {
+    pub event_name: String,
+    pub year: u32,
+    pub session_name: String,
+    pub circuit_name: String,
+    /// Date of the session as YYYY-MM-DD.
+    pub date: String,
+    #[serde(default)]
+    pub weather: Option<WeatherData>,
+    #[serde(default)]
+    pub corners: Vec<CornerData>
+}
+
+#[derive(Debug)]
 pub struct CompleteDriverData {
     pub telemetry: Vec<DriverTelemetryData>,
     pub lap: LapData,
@@ -87,7 +145,7 @@ pub fn fetch(framerate: u32, year: u32, country: &str, driver1: &str, driver2: &
             Ok(status) if status.success() => (),
             Ok(status) => {
                 if let Some(status_code) = status.code() {
-                    return Err(String::from(format!("Script reported an error code {} when fetching data", status_code)));
+                    return Err(format!("Script reported an error code {} when fetching data", status_code));
                 } else {
                     return Err(String::from("Script reported an unknown error when fetching data"));
                 }
@@ -100,4 +158,13 @@ pub fn fetch(framerate: u32, year: u32, country: &str, driver1: &str, driver2: &
     let d2_data = read_and_parse_driver_data(2)?;
 
     Ok((d1_data, d2_data))     
-}
\ No newline at end of file
+}
+
+/// Reads the session metadata exported by the last `fetch`.
+pub fn read_session_data() -> Result<SessionData, String> {
+    let session_json = fs::read_to_string("data/session_data.json")
+        .map_err(|_| String::from("Unable to read json file"))?;
+
+    serde_json::from_str(&session_json)
+        .map_err(|_| String::from("Unable to parse json file"))
+}
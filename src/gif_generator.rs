@@ -2,84 +2,474 @@ mod image_resize;
 mod drawing_utils;
 mod hud_overlay;
 mod telemetry_plot;
-mod gif_consts;
 mod track_map;
+mod text_cache;
+mod compositor;
+mod benchmark;
+mod render_config;
+mod layout;
+mod delta_plot;
+mod title;
+mod panel;
+mod theme;
+mod fonts;
+mod header;
+mod watermark;
+mod cards;
+mod svg_export;
+mod playback;
+mod lap_portion;
+mod follow_camera;
+mod trace_coloring;
+mod driver_colors;
+mod units;
+mod tyres;
+#[cfg(test)]
+mod test_utils;
 
-use std::{cmp::max, io::BufWriter};
-use std::fs::{self, File};
+use std::{cmp::max, io::BufWriter, iter::repeat_n};
+use std::fs::File;
 use std::io::Write;
-use ab_glyph::FontRef;
-use image::{codecs::gif::GifEncoder, imageops::overlay, 
-    Delay, Frame, RgbaImage};
+use image::{codecs::gif::GifEncoder, Delay, Frame, ImageFormat, Rgba, RgbaImage};
 use track_map::TrackMap;
-use crate::data_fetcher::CompleteDriverData;
+use crate::data_fetcher::{CompleteDriverData, SessionData};
 use image_resize::*;
 use drawing_utils::*;
 use hud_overlay::*;
 use telemetry_plot::*;
-use gif_consts::*;
+use text_cache::*;
+use compositor::*;
+use delta_plot::*;
+use title::*;
+use panel::*;
+use fonts::*;
+use header::*;
+use watermark::*;
+use cards::*;
+use svg_export::*;
+use playback::*;
+use lap_portion::*;
+use follow_camera::*;
+use trace_coloring::*;
+use driver_colors::*;
+use tyres::*;
 
-fn save_frame_to_gif<W>(encoder: &mut GifEncoder<W>, output_buffer: RgbaImage, framerate: u32)
+pub use render_config::{Orientation, RenderConfig, RESOLUTION_PRESETS};
+pub use theme::{Theme, THEME_NAMES};
+pub use fonts::FontConfig;
+pub use watermark::{Corner, WatermarkConfig};
+pub use playback::SlowMotion;
+pub use lap_portion::LapPortion;
+pub use follow_camera::FollowCameraConfig;
+pub use trace_coloring::TraceColoring;
+pub use units::Units;
+pub use layout::{Grid, GridCell, Layout, PanelKind, PanelSpec, Rect};
+
+pub use benchmark::run_benchmark;
+
+/// Options controlling how the comparison animation is rendered.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// Sampling frequency the telemetry was fetched with. Also the frames per
+    /// second of the animation unless `config.output_framerate` is set.
+    pub framerate: u32,
+    /// Print "Frame i / n" to stdout after each rendered frame.
+    pub print_progress: bool,
+    /// Dimensions, fonts and theme.
+    pub config: RenderConfig,
+    /// Panels to compose. Defaults to the arrangement given by `config`.
+    pub layout: Option<Layout>,
+    /// Event metadata shown by `header` panels.
+    pub session: Option<SessionData>
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions { framerate: 20, print_progress: false, config: RenderConfig::default(), layout: None,
+            session: None }
+    }
+}
+
+impl RenderOptions {
+    /// Returns the given layout, or the built-in one, with a header if
+    /// session data is available.
+    pub fn layout(&self) -> Layout {
+        self.layout.clone().unwrap_or_else(|| match self.session {
+            Some(_) => Layout::from_config_with_header(&self.config),
+            None => Layout::from_config(&self.config)
+        })
+    }
+
+    /// Checks the config, and that the header fits if the built-in layout shows it.
+    pub fn validate(&self) -> Result<(), String> {
+        self.config.validate()?;
+
+        if self.layout.is_none() && self.session.is_some() && self.config.header {
+            self.config.validate_header()?;
+        }
+        Ok(())
+    }
+}
+
+fn save_frame_to_gif<W>(encoder: &mut GifEncoder<W>, output_buffer: RgbaImage, delay_ms: u32) -> Result<(), String>
 where
     W: Write, 
 {
     let frame = Frame::from_parts(output_buffer, 0, 0, 
-        Delay::from_numer_denom_ms(1000, framerate));
+        Delay::from_numer_denom_ms(delay_ms, 1));
+
+    encoder.encode_frame(frame).map_err(|e| format!("Unable to encode frame: {}", e))
+}
+
+pub fn generate_gif(complete_d1_data: CompleteDriverData, complete_d2_data: CompleteDriverData, output_path: &str, options: &RenderOptions) -> Result<(), String> {  
+    let output_gif = File::create(output_path)
+        .map_err(|e| format!("Unable to create file {}: {}", output_path, e))?;
+
+    generate_gif_to_writer(complete_d1_data, complete_d2_data, BufWriter::new(output_gif), options)
+}
+
+pub fn generate_gif_to_writer<W>(complete_d1_data: CompleteDriverData, complete_d2_data: CompleteDriverData, writer: W, options: &RenderOptions) -> Result<(), String>
+where
+    W: Write,
+{
+    if options.framerate == 0 {
+        return Err(String::from("Framerate must be greater than zero"));
+    }
+
+    options.validate()?;
+
+    let mut encoder = GifEncoder::new_with_speed(writer, 30);
+
+    render_frames(complete_d1_data, complete_d2_data, &options.config, &options.layout(), 
+        options.session.as_ref(), options.framerate, |frame, delay_ms, i, no_frames| {
+        if options.print_progress {
+            println!("Frame {} / {}", i, no_frames - 1);
+        }
+
+        // The encoder quantizes the frame in place and takes ownership of it,
+        // so this is the only copy of the frame buffer made per frame.
+        save_frame_to_gif(&mut encoder, frame.clone(), delay_ms)
+    })
+}
+
+// Creates the renderers of all panels of the layout, in drawing order.
+fn create_panels<'a>(panels: Vec<(PanelSpec, Rect)>, d1: &'a CompleteDriverData, d2: &'a CompleteDriverData, 
+    (d1_draw_color, d2_draw_color): (Rgba<u8>, Rgba<u8>), session: Option<&SessionData>, text_cache: &TextCache, 
+    config: &RenderConfig) -> Vec<PlacedPanel<'a>> {
+    panels.into_iter().map(|(spec, rect)| {
+        let panel = match spec.kind {
+            PanelKind::TrackMap => Panel::TrackMap(
+                TrackMap::new(d1, d2, d1_draw_color, d2_draw_color, text_cache, config, rect.size())),
+            PanelKind::FollowCamera => Panel::FollowCamera(
+                FollowCamera::new(d1, d2, d1_draw_color, d2_draw_color, config, rect.size())),
+            PanelKind::SpeedPlot => Panel::SpeedPlot(
+                TelemetryPlot::new(d1, d2, (d1_draw_color, d2_draw_color), session, text_cache, config, 
+                    rect.size())),
+            PanelKind::DeltaPlot => Panel::DeltaPlot(
+                DeltaPlot::new(d1, d2, d1_draw_color, d2_draw_color, text_cache, config, 
+                    rect.size())),
+            PanelKind::DriverStats => Panel::DriverStats(spec.driver.unwrap_or(1)),
+            PanelKind::Title => {
+                let text = spec.text.unwrap_or_else(|| 
+                    format!("{} vs {}", d1.driver.broadcast_name, d2.driver.broadcast_name));
+                Panel::Title(Title::new(&text, text_cache, config))
+            },
+            PanelKind::Header => Panel::Header(
+                Header::new(session, spec.text.as_deref(), text_cache, config))
+        };
+
+        PlacedPanel { panel, rect }
+    }).collect()
+}
+
+// Everything composed into the frames of the lap, with the laps prepared
+// for the layout's track map.
+struct Scene<'a> {
+    d1: &'a CompleteDriverData,
+    d2: &'a CompleteDriverData,
+    text_cache: &'a TextCache<'a>,
+
+    panels: Vec<PlacedPanel<'a>>,
+    hud: HUD<'a>,
+    watermark: Option<Watermark>,
+    compositor: Compositor
+}
+
+impl <'a> Scene<'a> {
+    // Number of frames until the slower driver finishes.
+    fn lap_length(&self) -> usize {
+        max(self.d1.telemetry.len(), self.d2.telemetry.len())
+    }
+
+    // Draws the next frame into every panel without composing it.
+    fn advance(&mut self) {
+        for panel in &mut self.panels {
+            panel.draw_next_frame();
+        }
+    }
+
+    fn draw_lap_frame(&mut self, frame: usize) {
+        self.advance();
+        self.compose_lap_frame(frame);
+    }
+
+    // Composes the panels as drawn so far into the frame.
+    fn compose_lap_frame(&mut self, frame: usize) {
+        self.compositor.clear();
+
+        for panel in &self.panels {
+            panel.compose(&mut self.compositor, &self.hud, frame);
+        }
+
+        if let Some(watermark) = &self.watermark {
+            watermark.compose(&mut self.compositor);
+        }
+    }
+
+    // Clears the frame and draws the card, which covers the whole canvas, into it.
+    fn draw_card(&mut self, card: &RgbaImage) {
+        self.compositor.clear();
+        self.compositor.blend(card, 0, 0);
+
+        if let Some(watermark) = &self.watermark {
+            watermark.compose(&mut self.compositor);
+        }
+    }
+
+    fn frame(&self) -> &RgbaImage {
+        self.compositor.frame()
+    }
+}
+
+// Resolved panels of the layout and the drivers' draw colors.
+type PreparedLaps = (Vec<(PanelSpec, Rect)>, (Rgba<u8>, Rgba<u8>));
+
+// Resolves the layout and prepares the laps for it: the telemetry is cropped
+// to the lap portion, the drivers' draw colors are picked and the telemetry
+// is fitted to the track map panel.
+fn prepare_laps(complete_d1_data: &mut CompleteDriverData, complete_d2_data: &mut CompleteDriverData, 
+    config: &RenderConfig, layout: &Layout) -> Result<PreparedLaps, String> {
+    if complete_d1_data.telemetry.is_empty() || complete_d2_data.telemetry.is_empty() {
+        return Err(String::from("Telemetry data is empty"));
+    }
+
+    if let Some(lap_portion) = &config.lap_portion {
+        lap_portion.crop(complete_d1_data)?;
+        lap_portion.crop(complete_d2_data)?;
+    }
+
+    check_trace_coloring(complete_d1_data, complete_d2_data, config)?;
+
+    let panels = layout.resolve(config)?;
+
+    let draw_colors = get_driver_colors(&mut complete_d1_data.driver, &mut complete_d2_data.driver, config);
+
+    // Without a track map, the telemetry is fitted to the first follow camera panel instead.
+    let track_rect = panels.iter().find(|(spec, _)| spec.kind == PanelKind::TrackMap)
+        .or_else(|| panels.iter().find(|(spec, _)| spec.kind == PanelKind::FollowCamera));
+
+    if let Some((_, track_rect)) = track_rect {
+        let (width, height) = (track_rect.width - 2 * config.padding, track_rect.height - 2 * config.padding);
+        if config.lap_portion.is_some() {
+            fit_data_to_dims(&mut complete_d1_data.telemetry, &mut complete_d2_data.telemetry, width, height);
+        } else {
+            resize_data_to_dims(&mut complete_d1_data.telemetry, &mut complete_d2_data.telemetry, width, height);
+        }
+        center_data_to_dims(&mut complete_d1_data.telemetry, &mut complete_d2_data.telemetry, 
+            track_rect.width, track_rect.height);
+    }
+
+    Ok((panels, draw_colors))
+}
+
+// Builds the scene of the laps in the layout and passes it to `f`.
+fn with_scene<R, F>(mut complete_d1_data: CompleteDriverData, mut complete_d2_data: CompleteDriverData, 
+    config: &RenderConfig, layout: &Layout, session: Option<&SessionData>, f: F) -> Result<R, String>
+where
+    F: FnOnce(&mut Scene) -> Result<R, String>,
+{
+    let (panels, draw_colors) = prepare_laps(&mut complete_d1_data, &mut complete_d2_data, config, layout)?;
+
+    let fonts = Fonts::load(&config.fonts)?;
+
+    let text_cache = TextCache::new(&fonts);
+
+    let panels = create_panels(panels, &complete_d1_data, &complete_d2_data, 
+        draw_colors, session, &text_cache, config);
+
+    let hud = HUD::new(&complete_d1_data, &complete_d2_data, &text_cache, config);
+
+    let watermark = match &config.watermark {
+        Some(watermark) => Some(Watermark::new(watermark, &text_cache, config.theme.foreground())?),
+        None => None
+    };
+
+    let compositor = Compositor::new(layout.width, layout.height, config.theme.background());
+
+    f(&mut Scene { d1: &complete_d1_data, d2: &complete_d2_data, text_cache: &text_cache, 
+        panels, hud, watermark, compositor })
+}
 
-    encoder.encode_frame(frame).expect("Can't encode frame");
+// Content of a frame of the animation.
+#[derive(Clone, Copy, PartialEq)]
+enum Shot {
+    TitleCard,
+    Lap(usize),
+    SummaryCard
 }
 
-fn get_encoder(output_path: &str) -> GifEncoder<BufWriter<File>> {
-    let output_gif = fs::File::create(output_path).expect("Unable to create file");
-    let writer = std::io::BufWriter::new(output_gif);
-    GifEncoder::new_with_speed(writer, 30)
+// Renders every frame of the animation into a single reused frame buffer
+// and passes it to `on_frame` along with its delay in ms, the frame index and
+// the frame count. The lap is preceded by the title card and the held first
+// frame, and followed by the held last frame and the summary card.
+fn render_frames<F>(complete_d1_data: CompleteDriverData, complete_d2_data: CompleteDriverData, 
+    config: &RenderConfig, layout: &Layout, session: Option<&SessionData>, framerate: u32, 
+    mut on_frame: F) -> Result<(), String>
+where
+    F: FnMut(&RgbaImage, u32, usize, usize) -> Result<(), String>,
+{
+    with_scene(complete_d1_data, complete_d2_data, config, layout, session, |scene| {
+        let canvas_size = (layout.width, layout.height);
+        let title_layer = (config.title_card_seconds > 0.0).then(|| 
+            title_card(scene.d1, scene.d2, session, scene.text_cache, config, canvas_size));
+        let summary_layer = (config.summary_card_seconds > 0.0).then(|| 
+            summary_card(scene.d1, scene.d2, scene.text_cache, config, canvas_size));
+
+        let output_framerate = config.output_framerate.unwrap_or(framerate);
+        let frame_ms = 1000.0 / output_framerate as f64;
+        let hold = |shot: Shot, seconds: f32| 
+            repeat_n((shot, frame_ms), (seconds * output_framerate as f32).round() as usize);
+
+        let lap = lap_timeline(scene.d1, scene.d2, session, framerate, output_framerate, 
+            config.playback_speed, &config.slow_motion)?;
+
+        let mut shots = Vec::new();
+        if title_layer.is_some() {
+            shots.extend(hold(Shot::TitleCard, config.title_card_seconds));
+        }
+        shots.extend(hold(Shot::Lap(0), config.intro_seconds));
+        shots.extend(lap.into_iter().map(|(i, duration)| (Shot::Lap(i), duration)));
+        // At least one frame after the longer lap, so that both final lap times are shown.
+        shots.extend(repeat_n((Shot::Lap(scene.lap_length()), frame_ms), 
+            ((config.outro_seconds * output_framerate as f32).round() as usize).max(1)));
+        if summary_layer.is_some() {
+            shots.extend(hold(Shot::SummaryCard, config.summary_card_seconds));
+        }
+
+        let delays = frame_delays(&shots.iter().map(|(_, duration)| *duration).collect::<Vec<_>>());
+        let no_frames = shots.len();
+        let mut drawn_shot = None;
+        let mut no_advanced_frames = 0;
+
+        for (frame_index, ((shot, _), delay)) in shots.into_iter().zip(delays).enumerate() {
+            if drawn_shot != Some(shot) {
+                match shot {
+                    Shot::TitleCard => if let Some(title_layer) = &title_layer {
+                        scene.draw_card(title_layer);
+                    },
+                    Shot::Lap(i) => {
+                        // Samples that are not shown are still drawn into the panels' traces.
+                        while no_advanced_frames <= i {
+                            scene.advance();
+                            no_advanced_frames += 1;
+                        }
+                        scene.compose_lap_frame(i);
+                    },
+                    Shot::SummaryCard => if let Some(summary_layer) = &summary_layer {
+                        scene.draw_card(summary_layer);
+                    }
+                }
+                drawn_shot = Some(shot);
+            }
+
+            on_frame(scene.frame(), delay, frame_index, no_frames)?;
+        }
+
+        Ok(())
+    })
 }
 
-pub fn generate_gif(mut complete_d1_data: CompleteDriverData, mut complete_d2_data: CompleteDriverData, output_path: &str, framerate: u32) {  
-    let mut encoder = get_encoder(output_path);
+// Renders only the state after both drivers have finished: the full traces
+// and the final HUD, without composing the frames before it.
+pub fn render_summary_image(complete_d1_data: CompleteDriverData, complete_d2_data: CompleteDriverData, 
+    options: &RenderOptions) -> Result<RgbaImage, String> {
+    options.validate()?;
+
+    with_scene(complete_d1_data, complete_d2_data, &options.config, &options.layout(), 
+        options.session.as_ref(), |scene| {
+        let final_frame = scene.lap_length();
+        for _ in 0..final_frame {
+            scene.advance();
+        }
+        scene.draw_lap_frame(final_frame);
+
+        Ok(scene.frame().clone())
+    })
+}
 
-    let regular_font = FontRef::try_from_slice(
-        include_bytes!("../static/fonts/OpenSans-Regular.ttf")).expect("Unable to load font");
-    let bold_font = FontRef::try_from_slice(
-        include_bytes!("../static/fonts/OpenSans-Bold.ttf")).expect("Unable to load font");
+pub fn generate_summary(complete_d1_data: CompleteDriverData, complete_d2_data: CompleteDriverData, 
+    output_path: &str, options: &RenderOptions) -> Result<(), String> {
+    let image = render_summary_image(complete_d1_data, complete_d2_data, options)?;
 
-    let (d1_draw_color, d2_draw_color) = 
-        get_driver_colors(&complete_d1_data.driver, &mut complete_d2_data.driver);
+    image.save_with_format(output_path, ImageFormat::Png)
+        .map_err(|e| format!("Unable to save image {}: {}", output_path, e))
+}
 
-    resize_data_to_dims(&mut complete_d1_data.telemetry, &mut complete_d2_data.telemetry, 
-        TRACK_WIDTH - 2 * PADDING, TRACK_HEIGHT - 2 * PADDING);
-    center_data_to_dims(&mut complete_d1_data.telemetry, &mut complete_d2_data.telemetry, 
-        TRACK_WIDTH, TRACK_HEIGHT);
+// Renders the final state of the comparison as an SVG document.
+pub fn render_svg(mut complete_d1_data: CompleteDriverData, mut complete_d2_data: CompleteDriverData, 
+    options: &RenderOptions) -> Result<String, String> {
+    options.validate()?;
 
-    let mut track_map = 
-        TrackMap::new(&complete_d1_data, &complete_d2_data, 
-            d1_draw_color, d2_draw_color);
+    let layout = options.layout();
+    let (panels, draw_colors) = prepare_laps(&mut complete_d1_data, &mut complete_d2_data, &options.config, &layout)?;
 
-    let mut telemetry_plot = 
-        TelemetryPlot::new(&complete_d1_data, &complete_d2_data, 
-            d1_draw_color, d2_draw_color, &regular_font);
+    let fonts = Fonts::load(&options.config.fonts)?;
+    let text_cache = TextCache::new(&fonts);
 
-    let hud = HUD::new(&complete_d1_data, &complete_d2_data, &regular_font, &bold_font);
+    SvgRenderer::new(&complete_d1_data, &complete_d2_data, draw_colors, &fonts, &text_cache, &options.config)
+        .render(&layout, &panels, options.session.as_ref())
+}
 
-    let no_frames = max(complete_d1_data.telemetry.len(), complete_d2_data.telemetry.len()) + 20;
-    for i in 0..no_frames {
-        println!("Frame {} / {}", i, no_frames - 1);
+pub fn generate_svg_to_writer<W>(complete_d1_data: CompleteDriverData, complete_d2_data: CompleteDriverData, 
+    mut writer: W, options: &RenderOptions) -> Result<(), String>
+where
+    W: Write,
+{
+    let svg = render_svg(complete_d1_data, complete_d2_data, options)?;
 
-        track_map.draw_next_frame();
-        telemetry_plot.draw_next_frame();
+    writer.write_all(svg.as_bytes()).map_err(|e| format!("Unable to write SVG: {}", e))
+}
 
-        let mut combined_img = 
-            RgbaImage::from_pixel(GIF_WIDTH, GIF_HEIGHT, BACKGROUND_COLOR);
+pub fn generate_svg(complete_d1_data: CompleteDriverData, complete_d2_data: CompleteDriverData, 
+    output_path: &str, options: &RenderOptions) -> Result<(), String> {
+    let output_svg = File::create(output_path)
+        .map_err(|e| format!("Unable to create file {}: {}", output_path, e))?;
 
-        overlay(&mut combined_img, &track_map.get_track_map(), 0, 0);
+    generate_svg_to_writer(complete_d1_data, complete_d2_data, BufWriter::new(output_svg), options)
+}
 
-        overlay(&mut combined_img, &telemetry_plot.get_telemetry_plot(), 
-            TELEMETRY_POSITION_X, TELEMETRY_POSITION_Y);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        overlay(&mut combined_img, &hud.get_hud(i), 
-            HUD_POSITION_X, HUD_POSITION_Y);
-        
-        save_frame_to_gif(&mut encoder, combined_img, framerate);
+    fn session() -> SessionData {
+        SessionData { event_name: String::from("Test Grand Prix"), year: 2024, session_name: String::from("Qualifying"),
+            circuit_name: String::from("Test Circuit"), date: String::from("2024-01-01"), weather: None, corners: Vec::new() }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn header_is_validated_only_when_shown() {
+        let config = RenderConfig { header_height: RenderConfig::default().track_height, ..RenderConfig::default() };
+        let options = RenderOptions { config, ..RenderOptions::default() };
+        assert!(options.validate().is_ok());
+
+        let with_session = RenderOptions { session: Some(session()), ..options.clone() };
+        assert!(with_session.validate().unwrap_err().contains("header"));
+
+        let custom_layout = RenderOptions { layout: Some(Layout::from_config(&options.config)), ..with_session.clone() };
+        assert!(custom_layout.validate().is_ok());
+
+        let header_off = RenderOptions { config: RenderConfig { header: false, ..options.config.clone() }, ..with_session };
+        assert!(header_off.validate().is_ok());
+    }
+}
@@ -0,0 +1,114 @@
+use std::f64::consts::PI;
+use std::io::sink;
+use std::time::{Duration, Instant};
+
+use image::codecs::gif::GifEncoder;
+
+use crate::data_fetcher::{CompleteDriverData, DriverData, DriverTelemetryData, LapData};
+
+use super::{render_frames, save_frame_to_gif, Layout, RenderConfig};
+
+const SESSION_START: i64 = 3_600_000;
+
+// Generates a lap around a distorted ellipse, roughly the size of a real
+// circuit in fastf1 coordinates, with `no_samples` samples at `framerate`.
+fn synthetic_lap(no_samples: usize, framerate: u32, phase: f64, name: &str, team_color: [u8; 4]) -> CompleteDriverData {
+    let sample_time = 1000 / framerate as i64;
+
+    let telemetry = (0..no_samples).map(|i| {
+        let t = i as f64 / no_samples as f64;
+        let angle = 2.0 * PI * t;
+
+        DriverTelemetryData {
+            x: (3000.0 * angle.cos() + 800.0 * (3.0 * angle + phase).cos()) as i32,
+            y: (2000.0 * angle.sin() + 500.0 * (2.0 * angle).sin()) as i32,
+            session_time: SESSION_START + i as i64 * sample_time,
+            speed: (220.0 + 90.0 * (6.0 * angle + phase).sin()) as i32,
+            relative_distance: t,
+            distance: None,
+            throttle: None,
+            brake: None
+        }
+    }).collect();
+
+    let lap_time = no_samples as i64 * sample_time;
+    let sector_time = lap_time / 3;
+
+    let lap = LapData {
+        lap_time: lap_time as i32,
+        sector1_time: sector_time as i32,
+        sector2_time: sector_time as i32,
+        sector3_time: (lap_time - 2 * sector_time) as i32,
+        sector1_session_time: SESSION_START + sector_time,
+        sector2_session_time: SESSION_START + 2 * sector_time,
+        sector3_session_time: SESSION_START + lap_time,
+        compound: None,
+        tyre_life: None,
+        fresh_tyre: None
+    };
+
+    let driver = DriverData {
+        broadcast_name: String::from(name),
+        team_name: String::from("Benchmark Racing"),
+        team_color
+    };
+
+    CompleteDriverData { telemetry, lap, driver }
+}
+
+fn print_result(label: &str, no_frames: usize, elapsed: Duration) {
+    println!("{}: {} frames in {:.2} s ({:.1} frames/s)", label, no_frames,
+        elapsed.as_secs_f64(), no_frames as f64 / elapsed.as_secs_f64());
+}
+
+/// Measures rendering speed for a synthetic lap, first for compositing alone
+/// and then including GIF encoding (written to a sink, so without disk I/O).
+pub fn run_benchmark(no_samples: usize, framerate: u32) -> Result<(), String> {
+    if framerate == 0 {
+        return Err(String::from("Framerate must be greater than zero"));
+    }
+
+    if no_samples < 2 {
+        return Err(String::from("Benchmark needs at least two samples"));
+    }
+
+    let lap = || (
+        synthetic_lap(no_samples, framerate, 0.0, "D RIVER", [54, 113, 198, 255]),
+        synthetic_lap(no_samples + no_samples / 100, framerate, 0.1, "D RIVERTWO", [100, 196, 255, 255])
+    );
+
+    let config = RenderConfig::default();
+    let layout = Layout::from_config(&config);
+
+    let (d1, d2) = lap();
+    let mut no_frames = 0;
+    let start = Instant::now();
+    render_frames(d1, d2, &config, &layout, None, framerate, |_, _, _, _| {
+        no_frames += 1;
+        Ok(())
+    })?;
+    print_result("Compositing", no_frames, start.elapsed());
+
+    let (d1, d2) = lap();
+    let mut encoder = GifEncoder::new_with_speed(sink(), 30);
+    let mut no_frames = 0;
+    let start = Instant::now();
+    render_frames(d1, d2, &config, &layout, None, framerate, |frame, delay_ms, _, _| {
+        no_frames += 1;
+        save_frame_to_gif(&mut encoder, frame.clone(), delay_ms)
+    })?;
+    print_result("Compositing and encoding", no_frames, start.elapsed());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert_eq!(run_benchmark(100, 0).unwrap_err(), "Framerate must be greater than zero");
+        assert_eq!(run_benchmark(1, 20).unwrap_err(), "Benchmark needs at least two samples");
+    }
+}
@@ -0,0 +1,119 @@
+use std::rc::Rc;
+
+use image::{imageops, Rgba, RgbaImage};
+
+use crate::data_fetcher::{CompleteDriverData, SessionData};
+
+use super::{FontStyle, RenderConfig, TextCache, HUD, TRANSPARENT};
+
+// Rows of a card. A row with a single cell is centered on the canvas, rows
+// with more cells form a table whose first column is aligned to the left
+// and the others to the right.
+type Rows = Vec<Vec<Rc<RgbaImage>>>;
+
+/// Returns the card shown before the lap: the event and both drivers' names.
+pub fn title_card(d1: &CompleteDriverData, d2: &CompleteDriverData, session: Option<&SessionData>,
+    text_cache: &TextCache, config: &RenderConfig, (width, height): (u32, u32)) -> RgbaImage {
+    let mut rows: Rows = Vec::new();
+
+    if let Some(session) = session {
+        rows.push(vec![text_cache.get_text(FontStyle::Bold, config.driver_font_size, config.theme.accent(),
+            &format!("{} {}", session.year, session.event_name))]);
+        rows.push(vec![text_cache.get_text(FontStyle::Regular, config.team_font_size, config.theme.foreground(),
+            &format!("{} · {}", session.session_name, session.circuit_name))]);
+    }
+
+    rows.push(vec![text_cache.get_text(FontStyle::Bold, config.lap_speed_font_size,
+        Rgba::from(d1.driver.team_color), &d1.driver.broadcast_name)]);
+    rows.push(vec![text_cache.get_text(FontStyle::Regular, config.driver_font_size,
+        config.theme.foreground(), "vs")]);
+    rows.push(vec![text_cache.get_text(FontStyle::Bold, config.lap_speed_font_size,
+        Rgba::from(d2.driver.team_color), &d2.driver.broadcast_name)]);
+
+    draw_rows(&rows, config, (width, height))
+}
+
+/// Returns the card shown after the lap: the gap between the drivers and
+/// a table of their lap and sector times and top speeds.
+pub fn summary_card(d1: &CompleteDriverData, d2: &CompleteDriverData, text_cache: &TextCache,
+    config: &RenderConfig, (width, height): (u32, u32)) -> RgbaImage {
+    let gap = d2.lap.lap_time - d1.lap.lap_time;
+    let headline = match gap {
+        0 => String::from("Dead heat"),
+        _ => {
+            let faster = if gap > 0 { d1 } else { d2 };
+            format!("{} faster by {:.3} s", faster.driver.broadcast_name, gap.abs() as f64 / 1000.0)
+        }
+    };
+
+    let text = |style: FontStyle, color: Rgba<u8>, text: &str|
+        text_cache.get_text(style, config.driver_font_size, color, text);
+    let foreground = config.theme.foreground();
+    let time_row = |label: &str, time1: i32, time2: i32, format_time: fn(i32) -> String| vec![
+        text(FontStyle::Regular, foreground, label),
+        text(FontStyle::Timing, foreground, &format_time(time1)),
+        text(FontStyle::Timing, foreground, &format_time(time2)),
+        text(FontStyle::Timing, foreground, &format!("{:+.3}", (time2 - time1) as f64 / 1000.0))
+    ];
+
+    let top_speed = |driver_data: &CompleteDriverData|
+        config.units.round_speed(driver_data.telemetry.iter().map(|t| t.speed).max().unwrap_or(0));
+    let (top_speed1, top_speed2) = (top_speed(d1), top_speed(d2));
+
+    let rows: Rows = vec![
+        vec![text_cache.get_text(FontStyle::Bold, config.lap_speed_font_size, config.theme.accent(), &headline)],
+        vec![
+            text(FontStyle::Regular, foreground, ""),
+            text(FontStyle::Bold, Rgba::from(d1.driver.team_color), &d1.driver.broadcast_name),
+            text(FontStyle::Bold, Rgba::from(d2.driver.team_color), &d2.driver.broadcast_name),
+            text(FontStyle::Regular, foreground, "Gap")
+        ],
+        time_row("Lap", d1.lap.lap_time, d2.lap.lap_time, HUD::get_str_time),
+        time_row("Sector 1", d1.lap.sector1_time, d2.lap.sector1_time, HUD::time_to_sector_time_str),
+        time_row("Sector 2", d1.lap.sector2_time, d2.lap.sector2_time, HUD::time_to_sector_time_str),
+        time_row("Sector 3", d1.lap.sector3_time, d2.lap.sector3_time, HUD::time_to_sector_time_str),
+        vec![
+            text(FontStyle::Regular, foreground, "Top speed"),
+            text(FontStyle::Timing, foreground, &format!("{} {}", top_speed1, config.units.speed_unit())),
+            text(FontStyle::Timing, foreground, &format!("{} {}", top_speed2, config.units.speed_unit())),
+            text(FontStyle::Timing, foreground, &format!("{:+}", top_speed2 - top_speed1))
+        ]
+    ];
+
+    draw_rows(&rows, config, (width, height))
+}
+
+fn draw_rows(rows: &Rows, config: &RenderConfig, (width, height): (u32, u32)) -> RgbaImage {
+    let row_margin = config.name_lap_speed_margin;
+    let column_margin = config.padding_lr;
+
+    let no_columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let column_widths: Vec<u32> = (0..no_columns).map(|column| rows.iter()
+        .filter(|row| row.len() > 1)
+        .filter_map(|row| row.get(column).map(|cell| cell.width()))
+        .max().unwrap_or(0)).collect();
+    let table_width = column_widths.iter().sum::<u32>() + column_margin * (no_columns as u32).saturating_sub(1);
+
+    let row_height = |row: &Vec<Rc<RgbaImage>>| row.iter().map(|cell| cell.height()).max().unwrap_or(0);
+    let total_height = rows.iter().map(row_height).sum::<u32>() + row_margin * (rows.len() as u32).saturating_sub(1);
+
+    let mut layer = RgbaImage::from_pixel(width, height, TRANSPARENT);
+    let mut y = (height as i64 - total_height as i64) / 2;
+
+    for row in rows {
+        if let [cell] = row.as_slice() {
+            imageops::replace(&mut layer, cell.as_ref(), (width as i64 - cell.width() as i64) / 2, y);
+        } else {
+            let mut x = (width as i64 - table_width as i64) / 2;
+            for (column, cell) in row.iter().enumerate() {
+                let dx = if column == 0 { 0 } else { column_widths[column] - cell.width() };
+                imageops::replace(&mut layer, cell.as_ref(), x + dx as i64, y);
+                x += (column_widths[column] + column_margin) as i64;
+            }
+        }
+
+        y += (row_height(row) + row_margin) as i64;
+    }
+
+    layer
+}
@@ -0,0 +1,83 @@
+use image::{Rgba, RgbaImage};
+
+use super::Rect;
+
+// Single frame buffer reused for the whole animation. Every frame starts as
+// a copy of the background and layers are alpha-blended straight into it,
+// so no intermediate images are allocated while rendering.
+pub struct Compositor {
+    background: RgbaImage,
+    frame: RgbaImage
+}
+
+impl Compositor {
+    pub fn new(width: u32, height: u32, background_color: Rgba<u8>) -> Compositor {
+        let background = RgbaImage::from_pixel(width, height, background_color);
+        let frame = background.clone();
+
+        Compositor { background, frame }
+    }
+
+    pub fn clear(&mut self) {
+        self.frame.copy_from_slice(&self.background);
+    }
+
+    pub fn frame(&self) -> &RgbaImage {
+        &self.frame
+    }
+
+    // Blends the layer over the frame, clipping it to the frame's bounds.
+    // The background is opaque, so the frame stays opaque and the blend
+    // reduces to a linear interpolation of the color channels.
+    pub fn blend(&mut self, layer: &RgbaImage, x: i64, y: i64) {
+        let frame_width = self.frame.width() as i64;
+        let frame_height = self.frame.height() as i64;
+
+        let x_start = (-x).clamp(0, layer.width() as i64);
+        let y_start = (-y).clamp(0, layer.height() as i64);
+        let x_end = (frame_width - x).clamp(x_start, layer.width() as i64);
+        let y_end = (frame_height - y).clamp(y_start, layer.height() as i64);
+
+        for layer_y in y_start..y_end {
+            for layer_x in x_start..x_end {
+                let source = layer.get_pixel(layer_x as u32, layer_y as u32);
+                let target = self.frame.get_pixel_mut((x + layer_x) as u32, (y + layer_y) as u32);
+
+                Self::blend_pixel(target, source);
+            }
+        }
+    }
+
+    // Fills the rect, which must lie within the frame, with an opaque color.
+    pub fn fill(&mut self, rect: Rect, color: Rgba<u8>) {
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                self.frame.put_pixel(x, y, color);
+            }
+        }
+    }
+
+    // Draws a one pixel wide border along the inside of the rect.
+    pub fn outline(&mut self, rect: Rect, color: Rgba<u8>) {
+        let (right, bottom) = (rect.x + rect.width - 1, rect.y + rect.height - 1);
+
+        self.fill(Rect::new(rect.x, rect.y, rect.width, 1), color);
+        self.fill(Rect::new(rect.x, bottom, rect.width, 1), color);
+        self.fill(Rect::new(rect.x, rect.y, 1, rect.height), color);
+        self.fill(Rect::new(right, rect.y, 1, rect.height), color);
+    }
+
+    fn blend_pixel(target: &mut Rgba<u8>, source: &Rgba<u8>) {
+        match source[3] {
+            0 => (),
+            255 => *target = *source,
+            alpha => {
+                let alpha = alpha as u32;
+                for c in 0..3 {
+                    let blended = source[c] as u32 * alpha + target[c] as u32 * (255 - alpha);
+                    target[c] = ((blended + 127) / 255) as u8;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::draw_line_segment_mut;
+
+use crate::data_fetcher::{CompleteDriverData, DriverTelemetryData};
+
+use super::{distance_axis, draw_antialiased_line_mut, draw_plot_base, plot_base_size, Axis, Compositor, DistanceRange, RenderConfig, TextCache, TRANSPARENT};
+
+const MIN_DELTA_RANGE: f64 = 0.1;
+
+// Plots the gap between the drivers along the lap. Above the zero line
+// driver 1 is ahead and the line takes their color, below it driver 2 is.
+pub struct DeltaPlot<'a> {
+    d2: &'a CompleteDriverData,
+    d1_color: Rgba<u8>,
+    d2_color: Rgba<u8>,
+
+    // Gap in seconds at every sample of driver 2.
+    deltas: Vec<f64>,
+    max_delta: f64,
+    distance_range: DistanceRange,
+
+    base_buffer: RgbaImage,
+    buffer: RgbaImage,
+    padding: u32,
+    axes_labels_margin: u32,
+
+    current_point: Option<(f32, f32)>,
+    current_frame: usize
+}
+
+impl <'a> DeltaPlot<'a> {
+    pub fn new(d1: &'a CompleteDriverData, d2: &'a CompleteDriverData,
+        d1_color: Rgba<u8>, d2_color: Rgba<u8>, text_cache: &TextCache, config: &RenderConfig,
+        (width, height): (u32, u32)) -> DeltaPlot<'a> {
+        let axes_labels_margin = config.telemetry_plot_axes_labels_margin;
+        let (base_width, base_height) = plot_base_size(config, width, height);
+
+        let deltas = Self::get_deltas(&d1.telemetry, &d2.telemetry);
+        let max_delta = Self::get_delta_range(&deltas);
+
+        let distance_range = DistanceRange::new(config, d1, d2);
+        let distance_axis = distance_axis(text_cache, config, distance_range, base_width - axes_labels_margin);
+        let delta_axis = Axis { label: String::from("DELTA"), ticks: Vec::new() };
+
+        let mut base_buffer = draw_plot_base(text_cache, config, base_width, base_height, &distance_axis, &delta_axis);
+        let zero_y = ((base_height - axes_labels_margin) / 2) as f32;
+        draw_line_segment_mut(&mut base_buffer,
+            (axes_labels_margin as f32 + 1.0, zero_y), (base_width as f32, zero_y), config.theme.grid());
+
+        DeltaPlot { d2, d1_color, d2_color, deltas, max_delta, base_buffer, distance_range,
+            buffer: RgbaImage::from_pixel(base_width - axes_labels_margin,
+                base_height - axes_labels_margin, TRANSPARENT),
+            padding: config.padding,
+            axes_labels_margin,
+            current_point: None,
+            current_frame: 0
+        }
+    }
+
+    pub fn draw_next_frame(&mut self) {
+        if let Some(new_point) = self.get_point(self.current_frame) {
+            if let Some(prev_point) = self.current_point {
+                let color = if self.deltas[self.current_frame] >= 0.0 { self.d1_color } else { self.d2_color };
+                draw_antialiased_line_mut(&mut self.buffer, prev_point, new_point, color, 1.0);
+            }
+            self.current_point = Some(new_point);
+        }
+
+        self.current_frame += 1;
+    }
+
+    pub fn compose(&self, compositor: &mut Compositor, x: i64, y: i64) {
+        let x = x + self.padding as i64;
+        let y = y + self.padding as i64;
+
+        compositor.blend(&self.base_buffer, x, y);
+        compositor.blend(&self.buffer, x + self.axes_labels_margin as i64, y);
+    }
+
+    fn get_point(&self, current_frame: usize) -> Option<(f32, f32)> {
+        let telemetry = self.d2.telemetry.get(current_frame)?;
+        let delta = self.deltas[current_frame];
+
+        let width = self.buffer.width() as f64;
+        let half_height = (self.buffer.height() / 2) as f64;
+
+        let x = (width * self.distance_range.position(telemetry)) as f32;
+        let y = (half_height - half_height * delta / self.max_delta) as f32;
+
+        Some((x.min(self.buffer.width() as f32 - 1.0), y.min(self.buffer.height() as f32 - 1.0)))
+    }
+
+    // The plot spans from -range to range seconds.
+    pub fn get_delta_range(deltas: &[f64]) -> f64 {
+        deltas.iter().fold(MIN_DELTA_RANGE, |acc, delta| acc.max(delta.abs()))
+    }
+
+    // Time (ms since the start of the lap) at which the driver passed the
+    // given relative distance, interpolated between samples.
+    fn time_at_distance(telemetry: &[DriverTelemetryData], relative_distance: f64) -> f64 {
+        let start = telemetry[0].session_time;
+        let index = telemetry.partition_point(|t| t.relative_distance <= relative_distance);
+
+        if index == 0 {
+            return 0.0;
+        }
+        if index == telemetry.len() {
+            return (telemetry[index - 1].session_time - start) as f64;
+        }
+
+        let prev = &telemetry[index - 1];
+        let next = &telemetry[index];
+        let span = next.relative_distance - prev.relative_distance;
+        let ratio = if span > 0.0 { (relative_distance - prev.relative_distance) / span } else { 0.0 };
+
+        (prev.session_time - start) as f64 + ratio * (next.session_time - prev.session_time) as f64
+    }
+
+    pub fn get_deltas(d1: &[DriverTelemetryData], d2: &[DriverTelemetryData]) -> Vec<f64> {
+        let start = d2[0].session_time;
+
+        d2.iter().map(|t| {
+            let d1_time = Self::time_at_distance(d1, t.relative_distance);
+            ((t.session_time - start) as f64 - d1_time) / 1000.0
+        }).collect()
+    }
+}
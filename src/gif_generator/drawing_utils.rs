@@ -1,58 +1,73 @@
-use std::cmp::{min, max};
-
-use imageproc::drawing::{BresenhamLineIter, draw_filled_circle_mut};
 use image::{Rgba, RgbaImage};
-use crate::data_fetcher::DriverData;
-
-// Adapted from imageproc::drawing::draw_line_segment_mut
-fn draw_thick_line_segment_mut(image: &mut RgbaImage, start: (f32, f32), end: (f32, f32), color: Rgba<u8>, radius: i32)
-{
-    let line_iterator = BresenhamLineIter::new(start, end);
-    for point in line_iterator {
-        draw_filled_circle_mut(image, point, radius, color);
-    }
-}
 
-pub fn draw_thick_line_mut(image: &mut RgbaImage, start: (i32, i32), end: (i32, i32), color: Rgba<u8>, thickness: i32) {
-    let radius = (thickness + 1) / 2;
+// Background of the layers which are drawn on and then overlaid.
+pub const TRANSPARENT: Rgba<u8> = Rgba([255, 255, 255, 0]);
+
+// Width of the drivers' traces for the configured thickness.
+pub fn trace_width(thickness: i32) -> f32 {
+    (2 * ((thickness + 1) / 2) + 1) as f32
+}
 
-    let start_f32 = (start.0 as f32, start.1 as f32);
-    let end_f32 = (end.0 as f32, end.1 as f32);
+fn distance_to_segment((x, y): (f32, f32), (x1, y1): (f32, f32), (x2, y2): (f32, f32)) -> f32 {
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 { (((x - x1) * dx + (y - y1) * dy) / length_squared).clamp(0.0, 1.0) } else { 0.0 };
 
-    draw_thick_line_segment_mut(image, start_f32, end_f32, color, radius);
+    ((x - x1 - t * dx).powi(2) + (y - y1 - t * dy).powi(2)).sqrt()
 }
 
-// Adapted from: https://stackoverflow.com/a/37675777
-fn get_complementary_color(color: [u8; 4]) -> [u8; 4] {
-    let r = color[0];
-    let g = color[1];
-    let b = color[2];
-    let a = color[3];
+// Draws an anti-aliased line of the given width with round caps, so that the
+// segments of a polyline drawn one by one meet in round joins. Pixel (x, y)
+// is centered at (x, y) and covered by how far it lies within the line.
+// A pixel keeps the larger of its alpha and the new one, so the overlapping
+// ends of consecutive segments are not blended twice.
+pub fn draw_antialiased_line_mut(image: &mut RgbaImage, start: (f32, f32), end: (f32, f32), color: Rgba<u8>, width: f32) {
+    let radius = width / 2.0;
+    let reach = radius + 1.0;
 
-    let max_rgb = max(r, max(g, b)) as u16;
-    let min_rgb = min(r, min(g, b)) as u16;
+    let x_min = (start.0.min(end.0) - reach).floor().max(0.0) as i64;
+    let y_min = (start.1.min(end.1) - reach).floor().max(0.0) as i64;
+    let x_max = (start.0.max(end.0) + reach).ceil().min(image.width() as f32 - 1.0) as i64;
+    let y_max = (start.1.max(end.1) + reach).ceil().min(image.height() as f32 - 1.0) as i64;
 
-    let c_r = (max_rgb + min_rgb - r as u16) as u8;
-    let c_g = (max_rgb + min_rgb - g as u16) as u8;
-    let c_b = (max_rgb + min_rgb - b as u16) as u8;
+    for y in y_min..=y_max {
+        for x in x_min..=x_max {
+            let distance = distance_to_segment((x as f32, y as f32), start, end);
+            let coverage = (radius + 0.5 - distance).clamp(0.0, 1.0);
+            let alpha = (coverage * color[3] as f32).round() as u8;
 
-    [c_r, c_g, c_b, a]
+            let pixel = image.get_pixel_mut(x as u32, y as u32);
+            if alpha > pixel[3] {
+                *pixel = Rgba([color[0], color[1], color[2], alpha]);
+            }
+        }
+    }
 }
 
-// Returns driver colors coresponding to team's colors in Rgba<u8> with added transparency.
-// If both drivers are from the same team, the second driver is assigned 
-// a complementary color or blue (no complementary color to white)
-pub fn get_driver_colors(driver1: &DriverData, driver2: &mut DriverData) -> (Rgba<u8>, Rgba<u8>) {
-    let mut d1_color = driver1.team_color.clone();
-    let mut d2_color = driver2.team_color.clone();
+// Draws the dashes of a dashed line from `start` to `end`, continuing the
+// `(dash, gap)` pattern at `phase` along it. Returns the phase at `end`, so
+// that the segments of a polyline drawn one by one form a continuous pattern.
+pub fn draw_dashed_line_mut(image: &mut RgbaImage, start: (f32, f32), end: (f32, f32), color: Rgba<u8>, width: f32,
+    (dash, gap): (f32, f32), phase: f32) -> f32 {
+    let length = (end.0 - start.0).hypot(end.1 - start.1);
+    let period = dash + gap;
+    let point = |position: f32| {
+        let t = if length > 0.0 { position / length } else { 0.0 };
+        (start.0 + (end.0 - start.0) * t, start.1 + (end.1 - start.1) * t)
+    };
 
-    if d1_color == d2_color {
-        d2_color = if d2_color == [255, 255, 255, 255] {[102, 153, 255, 255]} else {get_complementary_color(d2_color)};
-        driver2.team_color = d2_color.clone();
-    }
+    let mut position = 0.0;
+    while position < length {
+        let in_period = (phase + position) % period;
 
-    d1_color[3] = 180;
-    d2_color[3] = 180;
+        if in_period < dash {
+            let dash_end = (position + dash - in_period).min(length);
+            draw_antialiased_line_mut(image, point(position), point(dash_end), color, width);
+            position = dash_end;
+        } else {
+            position += period - in_period;
+        }
+    }
 
-    return (Rgba::from(d1_color), Rgba::from(d2_color));
-}   
\ No newline at end of file
+    (phase + length) % period
+}
@@ -0,0 +1,207 @@
+use image::Rgba;
+
+use crate::data_fetcher::DriverData;
+
+use super::RenderConfig;
+
+// Smallest CIELAB distance (CIE76) of a driver's color to the background,
+// and between the drivers' colors. A distance of about 2 is just noticeable.
+const MIN_BACKGROUND_DISTANCE: f64 = 35.0;
+const MIN_DRIVER_DISTANCE: f64 = 30.0;
+
+// Colors tried for the second driver besides hue and lightness variants of
+// their own color, for colors with too little chroma to rotate.
+const FALLBACK_COLORS: [[u8; 3]; 6] = [
+    [102, 153, 255], [255, 140, 0], [230, 60, 200], [60, 200, 90], [255, 220, 40], [240, 240, 240]
+];
+
+type Lab = (f64, f64, f64);
+
+fn srgb_to_linear(c: u8) -> f64 {
+    let c = c as f64 / 255.0;
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f64) -> u8 {
+    let c = if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// CIELAB under the D65 white point.
+fn rgb_to_lab([r, g, b]: [u8; 3]) -> Lab {
+    let (r, g, b) = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f64| if t > 216.0 / 24389.0 { t.cbrt() } else { (24389.0 / 27.0 * t + 16.0) / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+// Colors outside of the sRGB gamut are clipped channel by channel.
+fn lab_to_rgb((l, a, b): Lab) -> [u8; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let (fx, fz) = (fy + a / 500.0, fy - b / 200.0);
+    let f_inv = |t: f64| if t.powi(3) > 216.0 / 24389.0 { t.powi(3) } else { (116.0 * t - 16.0) * 27.0 / 24389.0 };
+    let (x, y, z) = (f_inv(fx) * 0.95047, f_inv(fy), f_inv(fz) * 1.08883);
+
+    [
+        linear_to_srgb(3.2406 * x - 1.5372 * y - 0.4986 * z),
+        linear_to_srgb(-0.9689 * x + 1.8758 * y + 0.0415 * z),
+        linear_to_srgb(0.0557 * x - 0.2040 * y + 1.0570 * z)
+    ]
+}
+
+fn distance(c1: [u8; 3], c2: [u8; 3]) -> f64 {
+    let ((l1, a1, b1), (l2, a2, b2)) = (rgb_to_lab(c1), rgb_to_lab(c2));
+    ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+}
+
+/// Moves the color's lightness away from the background until it stands out
+/// against it, keeping its hue.
+pub fn contrast_with_background(color: [u8; 3], background: [u8; 3]) -> [u8; 3] {
+    let (l, a, b) = rgb_to_lab(color);
+    let direction = if rgb_to_lab(background).0 < 50.0 { 1.0 } else { -1.0 };
+
+    (0..=20).map(|step| lab_to_rgb(((l + direction * step as f64 * 5.0).clamp(0.0, 100.0), a, b)))
+        .find(|candidate| distance(*candidate, background) >= MIN_BACKGROUND_DISTANCE)
+        .unwrap_or(color)
+}
+
+// Picks the color of the second driver that is most distinct from both the
+// first driver's color and the background: their own color, its hue
+// rotations and lightness variants, or one of the fallback colors.
+fn distinct_color(color: [u8; 3], other: [u8; 3], background: [u8; 3]) -> [u8; 3] {
+    let (l, a, b) = rgb_to_lab(color);
+    let (chroma, hue) = (a.hypot(b), b.atan2(a));
+
+    let hue_variants = [60.0f64, 120.0, 180.0, 240.0, 300.0].map(|degrees| {
+        let hue = hue + degrees.to_radians();
+        lab_to_rgb((l, chroma * hue.cos(), chroma * hue.sin()))
+    });
+    let lightness_variants = [-30.0, 30.0].map(|dl| lab_to_rgb(((l + dl).clamp(0.0, 100.0), a, b)));
+
+    let score = |candidate: &[u8; 3]| distance(*candidate, other).min(distance(*candidate, background));
+
+    std::iter::once(color)
+        .chain(hue_variants)
+        .chain(lightness_variants)
+        .chain(FALLBACK_COLORS)
+        .max_by(|c1, c2| score(c1).total_cmp(&score(c2)))
+        .unwrap_or(color)
+}
+
+/// Returns the drivers' draw colors with the theme's line alpha.
+///
+/// Colors set in the config are used as they are. Team colors are made to
+/// stand out against the background, and if both drivers' colors are too
+/// similar, e.g. for team-mates, the second driver gets a more distinct one.
+/// The drivers' colors are updated, so that the HUD uses the same ones.
+pub fn get_driver_colors(driver1: &mut DriverData, driver2: &mut DriverData, config: &RenderConfig) -> (Rgba<u8>, Rgba<u8>) {
+    let background = [0, 1, 2].map(|c| config.theme.background()[c]);
+    let rgb = |color: [u8; 4]| [color[0], color[1], color[2]];
+
+    let d1_color = config.driver1_color.unwrap_or_else(|| contrast_with_background(rgb(driver1.team_color), background));
+    let d2_color = config.driver2_color.unwrap_or_else(|| {
+        let color = contrast_with_background(rgb(driver2.team_color), background);
+
+        if distance(color, d1_color) < MIN_DRIVER_DISTANCE {
+            distinct_color(color, d1_color, background)
+        } else {
+            color
+        }
+    });
+
+    driver1.team_color = [d1_color[0], d1_color[1], d1_color[2], 255];
+    driver2.team_color = [d2_color[0], d2_color[1], d2_color[2], 255];
+
+    let alpha = config.theme.line_alpha;
+    (Rgba([d1_color[0], d1_color[1], d1_color[2], alpha]), Rgba([d2_color[0], d2_color[1], d2_color[2], alpha]))
+}
+
+/// Returns the `(dash, gap)` pattern of the second driver's trace for the
+/// given trace width, if it is dashed to tell team-mates apart where the
+/// traces overlap.
+pub fn driver2_dashes(driver1: &DriverData, driver2: &DriverData, config: &RenderConfig, line_width: f32) -> Option<(f32, f32)> {
+    (config.dash_teammates && driver1.team_name == driver2.team_name).then_some((4.0 * line_width, 2.0 * line_width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_generator::{test_utils::lap, Theme};
+
+    fn drivers(color1: [u8; 3], color2: [u8; 3]) -> (DriverData, DriverData) {
+        let (mut d1, mut d2) = (lap(&[200; 2], 50).driver, lap(&[200; 2], 50).driver);
+        d1.team_color = [color1[0], color1[1], color1[2], 255];
+        d2.team_color = [color2[0], color2[1], color2[2], 255];
+        d2.team_name = String::from("Other Racing");
+        (d1, d2)
+    }
+
+    fn rgb(color: Rgba<u8>) -> [u8; 3] {
+        [color[0], color[1], color[2]]
+    }
+
+    #[test]
+    fn keeps_distinct_team_colors() {
+        let config = RenderConfig::default();
+        let (mut d1, mut d2) = drivers([255, 135, 0], [0, 210, 190]);
+
+        let (c1, c2) = get_driver_colors(&mut d1, &mut d2, &config);
+
+        assert_eq!((rgb(c1), rgb(c2)), ([255, 135, 0], [0, 210, 190]));
+        assert_eq!((c1[3], c2[3]), (config.theme.line_alpha, config.theme.line_alpha));
+    }
+
+    #[test]
+    fn contrasts_colors_with_the_background() {
+        for theme in ["dark", "light"] {
+            let config = RenderConfig { theme: Theme::named(theme).unwrap(), ..RenderConfig::default() };
+            let background = rgb(config.theme.background());
+            let (mut d1, mut d2) = drivers(background, [128, 128, 128]);
+
+            let (c1, c2) = get_driver_colors(&mut d1, &mut d2, &config);
+
+            assert!(distance(rgb(c1), background) >= MIN_BACKGROUND_DISTANCE);
+            assert!(distance(rgb(c2), background) >= MIN_BACKGROUND_DISTANCE);
+            assert_eq!(&d1.team_color[..3], &rgb(c1));
+        }
+    }
+
+    #[test]
+    fn separates_similar_colors() {
+        for color in [[54, 113, 198], [255, 255, 255], [220, 0, 0], [0, 0, 0]] {
+            let config = RenderConfig::default();
+            let (mut d1, mut d2) = drivers(color, color);
+
+            let (c1, c2) = get_driver_colors(&mut d1, &mut d2, &config);
+
+            assert!(distance(rgb(c1), rgb(c2)) >= MIN_DRIVER_DISTANCE, "{:?}", color);
+            assert_eq!(&d2.team_color[..3], &rgb(c2));
+        }
+    }
+
+    #[test]
+    fn uses_configured_colors_as_they_are() {
+        let config = RenderConfig { driver1_color: Some([1, 2, 3]), driver2_color: Some([1, 2, 4]), ..RenderConfig::default() };
+        let (mut d1, mut d2) = drivers([255, 135, 0], [0, 210, 190]);
+
+        let (c1, c2) = get_driver_colors(&mut d1, &mut d2, &config);
+
+        assert_eq!((rgb(c1), rgb(c2)), ([1, 2, 3], [1, 2, 4]));
+    }
+
+    #[test]
+    fn dashes_team_mates() {
+        let (d1, mut d2) = drivers([0, 0, 0], [0, 0, 0]);
+        let config = RenderConfig::default();
+        assert_eq!(driver2_dashes(&d1, &d2, &config, 3.0), None);
+
+        d2.team_name = d1.team_name.clone();
+        assert_eq!(driver2_dashes(&d1, &d2, &config, 3.0), Some((12.0, 6.0)));
+        assert_eq!(driver2_dashes(&d1, &d2, &RenderConfig { dash_teammates: false, ..config }, 3.0), None);
+    }
+}
@@ -0,0 +1,297 @@
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::data_fetcher::CompleteDriverData;
+
+use super::{draw_antialiased_line_mut, draw_dashed_line_mut, driver2_dashes, trace_width, Compositor, Rect, RenderConfig, TraceColors, TRANSPARENT};
+
+// Zoom is limited as the traces are drawn at full zoomed size.
+const MAX_ZOOM: f32 = 8.0;
+
+/// Zoom and panning of `follow_camera` panels.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FollowCameraConfig {
+    /// Magnification relative to the track map, between 1 and 8.
+    pub zoom: f32,
+    /// Part of the distance to the leading car the camera moves by every
+    /// sample, between 0 and 1. Lower values pan more smoothly, 1 keeps the
+    /// leading car centered.
+    pub smoothing: f32
+}
+
+impl Default for FollowCameraConfig {
+    fn default() -> Self {
+        FollowCameraConfig { zoom: 4.0, smoothing: 0.2 }
+    }
+}
+
+impl FollowCameraConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1.0..=MAX_ZOOM).contains(&self.zoom) {
+            return Err(format!("Follow camera zoom must be between 1 and {}", MAX_ZOOM));
+        }
+
+        if !(self.smoothing > 0.0 && self.smoothing <= 1.0) {
+            return Err(String::from("Follow camera smoothing must be greater than 0 and at most 1"));
+        }
+
+        Ok(())
+    }
+}
+
+struct FollowCameraDriverData<'a> {
+    data: &'a CompleteDriverData,
+    // The driver's trace within the view.
+    view: RgbaImage,
+    color: Rgba<u8>,
+    dashes: Option<(f32, f32)>,
+    // Phase of the dash pattern at the start of every segment.
+    dash_phases: Vec<f32>,
+    // Zoomed positions of the samples.
+    points: Vec<(f32, f32)>,
+    // Segments reaching into every view-sized cell of the zoomed track map,
+    // in the order they are driven.
+    cells: HashMap<(i64, i64), Vec<usize>>
+}
+
+// A view of the track map magnified around the leading car. Every frame, the
+// segments of the traces within the view are drawn from the telemetry
+// transformed for the track map, scaled by the zoom, so that memory does not
+// grow with the zoom. The segments are looked up in the cells the view
+// overlaps, so that drawing a frame does not take longer along the lap.
+pub struct FollowCamera<'a> {
+    d1: FollowCameraDriverData<'a>,
+    d2: FollowCameraDriverData<'a>,
+
+    config: FollowCameraConfig,
+    background: Rgba<u8>,
+    border: Rgba<u8>,
+    line_width: f32,
+    colors: TraceColors,
+
+    // Center of the view in zoomed coordinates.
+    center: (f64, f64),
+    current_frame: usize
+}
+
+impl <'a> FollowCamera<'a> {
+    pub fn new(d1_complete_data: &'a CompleteDriverData, d2_complete_data: &'a CompleteDriverData,
+        d1_color: Rgba<u8>, d2_color: Rgba<u8>, config: &RenderConfig, (width, height): (u32, u32)) -> FollowCamera<'a> {
+        let zoom = config.follow_camera.zoom;
+        let line_width = trace_width(config.thickness);
+
+        let driver = |data: &'a CompleteDriverData, color, dashes: Option<(f32, f32)>| {
+            let points: Vec<(f32, f32)> = (0..data.telemetry.len()).map(|frame| Self::point(data, frame, zoom)).collect();
+
+            FollowCameraDriverData {
+                data,
+                view: RgbaImage::from_pixel(width, height, TRANSPARENT),
+                color,
+                dashes,
+                dash_phases: dashes.map_or_else(Vec::new, |dashes| Self::dash_phases(data, zoom, dashes)),
+                cells: Self::cells(&points, (width, height), Self::reach(line_width)),
+                points
+            }
+        };
+
+        FollowCamera {
+            d1: driver(d1_complete_data, d1_color, None),
+            d2: driver(d2_complete_data, d2_color, 
+                driver2_dashes(&d1_complete_data.driver, &d2_complete_data.driver, config, line_width)),
+            config: config.follow_camera,
+            background: config.theme.background(),
+            border: config.theme.grid(),
+            line_width,
+            colors: TraceColors::new(d1_complete_data, d2_complete_data, config),
+            center: Self::target(d1_complete_data, d2_complete_data, 0, zoom),
+            current_frame: 0
+        }
+    }
+
+    fn point(driver_data: &CompleteDriverData, frame: usize, zoom: f32) -> (f32, f32) {
+        let t = &driver_data.telemetry[frame];
+        (t.y as f32 * zoom, t.x as f32 * zoom)
+    }
+
+    fn dash_phases(driver_data: &CompleteDriverData, zoom: f32, (dash, gap): (f32, f32)) -> Vec<f32> {
+        (1..driver_data.telemetry.len()).scan(0.0, |phase, frame| {
+            let (start, end) = (Self::point(driver_data, frame - 1, zoom), Self::point(driver_data, frame, zoom));
+            let segment_phase = *phase;
+            *phase = (*phase + (end.0 - start.0).hypot(end.1 - start.1)) % (dash + gap);
+            Some(segment_phase)
+        }).collect()
+    }
+
+    // How far beyond its points a segment can color pixels.
+    fn reach(line_width: f32) -> f32 {
+        line_width / 2.0 + 1.0
+    }
+
+    // Range of the cells of the given size an interval of the zoomed track
+    // map lies in.
+    fn cell_range(start: f32, end: f32, size: u32) -> RangeInclusive<i64> {
+        let size = size.max(1) as f32;
+        (start / size).floor() as i64..=(end / size).floor() as i64
+    }
+
+    fn cells(points: &[(f32, f32)], (width, height): (u32, u32), reach: f32) -> HashMap<(i64, i64), Vec<usize>> {
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+
+        for (segment, pair) in points.windows(2).enumerate() {
+            let (p1, p2) = (pair[0], pair[1]);
+            for column in Self::cell_range(p1.0.min(p2.0) - reach, p1.0.max(p2.0) + reach, width) {
+                for row in Self::cell_range(p1.1.min(p2.1) - reach, p1.1.max(p2.1) + reach, height) {
+                    cells.entry((column, row)).or_default().push(segment);
+                }
+            }
+        }
+
+        cells
+    }
+
+    // The first `driven` segments in the cells the view at `origin` overlaps,
+    // in lap order like on the track map.
+    fn segments_in_view(cells: &HashMap<(i64, i64), Vec<usize>>, origin: (f32, f32), (width, height): (u32, u32), 
+        driven: usize) -> Vec<usize> {
+        let mut segments = Vec::new();
+        for column in Self::cell_range(origin.0, origin.0 + width as f32, width) {
+            for row in Self::cell_range(origin.1, origin.1 + height as f32, height) {
+                if let Some(cell) = cells.get(&(column, row)) {
+                    segments.extend_from_slice(&cell[..cell.partition_point(|&segment| segment < driven)]);
+                }
+            }
+        }
+
+        segments.sort_unstable();
+        segments.dedup();
+        segments
+    }
+
+    // Zoomed position of the driver further along the lap among those still
+    // on track, or once both have finished, of the driver who finished last.
+    fn target(d1: &CompleteDriverData, d2: &CompleteDriverData, frame: usize, zoom: f32) -> (f64, f64) {
+        let progress = |driver_data: &CompleteDriverData| driver_data.telemetry.get(frame)
+            .map_or((false, driver_data.telemetry.len() as f64), |t| (true, t.relative_distance));
+        let position = |driver_data: &CompleteDriverData| {
+            let t = &driver_data.telemetry[frame.min(driver_data.telemetry.len() - 1)];
+            (t.y as f64 * zoom as f64, t.x as f64 * zoom as f64)
+        };
+
+        if progress(d1) >= progress(d2) { position(d1) } else { position(d2) }
+    }
+
+    fn pan(center: (f64, f64), target: (f64, f64), smoothing: f32) -> (f64, f64) {
+        let smoothing = smoothing as f64;
+        (center.0 + (target.0 - center.0) * smoothing, center.1 + (target.1 - center.1) * smoothing)
+    }
+
+    /// Returns the zoomed center of the view after the whole lap, as shown
+    /// in the last frame.
+    pub fn final_center(d1: &CompleteDriverData, d2: &CompleteDriverData, config: &FollowCameraConfig) -> (f64, f64) {
+        let lap_length = d1.telemetry.len().max(d2.telemetry.len());
+
+        (0..=lap_length).fold(Self::target(d1, d2, 0, config.zoom), |center, frame|
+            Self::pan(center, Self::target(d1, d2, frame, config.zoom), config.smoothing))
+    }
+
+    pub fn draw_next_frame(&mut self) {
+        let zoom = self.config.zoom;
+
+        let target = Self::target(self.d1.data, self.d2.data, self.current_frame, zoom);
+        self.center = Self::pan(self.center, target, self.config.smoothing);
+
+        self.current_frame += 1;
+
+        for driver in [&mut self.d1, &mut self.d2] {
+            let (width, height) = driver.view.dimensions();
+            let origin = ((self.center.0.round() as i64 - width as i64 / 2) as f32,
+                (self.center.1.round() as i64 - height as i64 / 2) as f32);
+            let reach = Self::reach(self.line_width);
+            let visible = |p1: (f32, f32), p2: (f32, f32)| p1.0.max(p2.0) + reach >= 0.0 && p1.1.max(p2.1) + reach >= 0.0
+                && p1.0.min(p2.0) - reach <= width as f32 && p1.1.min(p2.1) - reach <= height as f32;
+
+            let driven = self.current_frame.min(driver.points.len().saturating_sub(1));
+
+            driver.view.fill(0);
+
+            for frame in Self::segments_in_view(&driver.cells, origin, (width, height), driven) {
+                let point = |frame: usize| {
+                    let (x, y) = driver.points[frame];
+                    (x - origin.0, y - origin.1)
+                };
+                let (p1, p2) = (point(frame), point(frame + 1));
+                if !visible(p1, p2) {
+                    continue;
+                }
+
+                let color = self.colors.segment_color(driver.data, driver.color, frame);
+                match driver.dashes {
+                    Some(dashes) => {
+                        draw_dashed_line_mut(&mut driver.view, p1, p2, color, self.line_width, dashes, driver.dash_phases[frame]);
+                    },
+                    None => draw_antialiased_line_mut(&mut driver.view, p1, p2, color, self.line_width)
+                }
+            }
+        }
+    }
+
+    pub fn compose(&self, compositor: &mut Compositor, rect: Rect) {
+        compositor.fill(rect, self.background);
+
+        for driver in [&self.d1, &self.d2] {
+            compositor.blend(&driver.view, rect.x as i64, rect.y as i64);
+        }
+
+        compositor.outline(rect, self.border);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_generator::test_utils::lap;
+
+    #[test]
+    fn follows_the_driver_still_on_track() {
+        let leader = lap(&[200; 10], 50);
+        let mut follower = lap(&[200; 20], 50);
+        for t in &mut follower.telemetry {
+            t.y = 100;
+        }
+
+        let position = |data: &CompleteDriverData, frame: usize| (data.telemetry[frame].y as f64, data.telemetry[frame].x as f64);
+
+        assert_eq!(FollowCamera::target(&leader, &follower, 5, 1.0), position(&leader, 5));
+        assert_eq!(FollowCamera::target(&leader, &follower, 12, 1.0), position(&follower, 12));
+        assert_eq!(FollowCamera::target(&follower, &leader, 12, 1.0), position(&follower, 12));
+        assert_eq!(FollowCamera::target(&leader, &follower, 25, 1.0), position(&follower, 19));
+    }
+
+    #[test]
+    fn finds_the_driven_segments_in_view() {
+        let points: Vec<(f32, f32)> = (0..200).map(|i| {
+            let angle = i as f32 / 200.0 * std::f32::consts::TAU;
+            (500.0 + 400.0 * angle.cos(), 500.0 + 300.0 * angle.sin())
+        }).collect();
+        let (size, reach) = ((120, 90), 2.5);
+        let cells = FollowCamera::cells(&points, size, reach);
+
+        for origin in [(0.0, 0.0), (-60.0, 430.0), (830.0, 400.0), (455.5, 733.25), (2000.0, 2000.0)] {
+            for driven in [0, 50, 150, 199] {
+                let in_view: Vec<usize> = (0..driven).filter(|&segment| {
+                    let (p1, p2) = (points[segment], points[segment + 1]);
+                    p1.0.max(p2.0) + reach >= origin.0 && p1.1.max(p2.1) + reach >= origin.1
+                        && p1.0.min(p2.0) - reach <= origin.0 + size.0 as f32 
+                        && p1.1.min(p2.1) - reach <= origin.1 + size.1 as f32
+                }).collect();
+                let found = FollowCamera::segments_in_view(&cells, origin, size, driven);
+
+                assert!(in_view.iter().all(|segment| found.contains(segment)), "{:?} {}", origin, driven);
+                assert!(found.windows(2).all(|pair| pair[0] < pair[1]) && found.iter().all(|&segment| segment < driven));
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs;
+
+use ab_glyph::{Font, FontArc};
+use serde::{Deserialize, Serialize};
+use ttf_parser::{name_id, Face};
+
+use super::FontStyle;
+
+/// Font files of the text styles. Styles without a file use the built-in
+/// OpenSans (the timing style uses OpenSans Regular, whose digits all have
+/// the same width, so times line up but other text is proportional).
+///
+/// Characters missing from a style's font are taken from the first font in
+/// `fallback` which has them, and then from the built-in OpenSans.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regular: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bold: Option<String>,
+    /// Used for the sector times and the cards' timing columns, e.g. a
+    /// monospace font.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timing: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>
+}
+
+// Fallback chains of every style, starting with the style's own font.
+pub struct Fonts {
+    regular: Vec<FontArc>,
+    bold: Vec<FontArc>,
+    timing: Vec<FontArc>
+}
+
+impl Fonts {
+    pub fn load(config: &FontConfig) -> Result<Fonts, String> {
+        let built_in_regular = FontArc::try_from_slice(
+            include_bytes!("../../static/fonts/OpenSans-Regular.ttf"))
+            .map_err(|_| String::from("Unable to load font"))?;
+        let built_in_bold = FontArc::try_from_slice(
+            include_bytes!("../../static/fonts/OpenSans-Bold.ttf"))
+            .map_err(|_| String::from("Unable to load font"))?;
+
+        let fallback = config.fallback.iter()
+            .map(|path| Self::load_file(path))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let chain = |path: &Option<String>, built_in: &FontArc| -> Result<Vec<FontArc>, String> {
+            let mut fonts = Vec::new();
+            if let Some(path) = path {
+                fonts.push(Self::load_file(path)?);
+            }
+            fonts.extend(fallback.iter().cloned());
+            fonts.push(built_in.clone());
+
+            // Without a file of its own the style starts with the built-in font.
+            if path.is_none() {
+                fonts.rotate_right(1);
+            }
+            Ok(fonts)
+        };
+
+        Ok(Fonts {
+            regular: chain(&config.regular, &built_in_regular)?,
+            bold: chain(&config.bold, &built_in_bold)?,
+            timing: chain(&config.timing, &built_in_regular)?
+        })
+    }
+
+    fn load_file(path: &str) -> Result<FontArc, String> {
+        let data = fs::read(path)
+            .map_err(|e| format!("Unable to read font file {}: {}", path, e))?;
+
+        FontArc::try_from_vec(data)
+            .map_err(|_| format!("Unable to parse font file {}", path))
+    }
+
+    fn chain(&self, style: FontStyle) -> &[FontArc] {
+        match style {
+            FontStyle::Regular => &self.regular,
+            FontStyle::Bold => &self.bold,
+            FontStyle::Timing => &self.timing
+        }
+    }
+
+    pub fn primary(&self, style: FontStyle) -> &FontArc {
+        &self.chain(style)[0]
+    }
+
+    // The first font of the style's chain which has a glyph for the character.
+    pub fn font_for(&self, style: FontStyle, c: char) -> &FontArc {
+        let chain = self.chain(style);
+        chain.iter().find(|font| font.glyph_id(c).0 != 0).unwrap_or(&chain[0])
+    }
+
+    /// Family names of the fonts of the style's chain, in fallback order.
+    pub fn families(&self, style: FontStyle) -> Vec<String> {
+        let mut families: Vec<String> = Vec::new();
+        for family in self.chain(style).iter().filter_map(Self::family) {
+            if !families.contains(&family) {
+                families.push(family);
+            }
+        }
+        families
+    }
+
+    /// Weight of the style's font, e.g. 400 for regular and 700 for bold.
+    pub fn weight(&self, style: FontStyle) -> u16 {
+        Self::face(self.primary(style)).map_or(400, |face| face.weight().to_number())
+    }
+
+    pub fn is_monospaced(&self, style: FontStyle) -> bool {
+        Self::face(self.primary(style)).is_some_and(|face| face.is_monospaced())
+    }
+
+    fn face(font: &FontArc) -> Option<Face<'_>> {
+        Face::parse(font.font_data(), 0).ok()
+    }
+
+    // The typographic family groups more weights than the legacy family, which
+    // e.g. names a semibold style as a family of its own.
+    fn family(font: &FontArc) -> Option<String> {
+        let face = Self::face(font)?;
+        let name = |id: u16| face.names().into_iter()
+            .find(|name| name.name_id == id && name.is_unicode())
+            .and_then(|name| name.to_string());
+
+        name(name_id::TYPOGRAPHIC_FAMILY).or_else(|| name(name_id::FAMILY))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_timing_digits_have_the_same_width() {
+        let fonts = Fonts::load(&FontConfig::default()).unwrap();
+        let font = fonts.primary(FontStyle::Timing);
+        let advance = |c: char| font.h_advance_unscaled(font.glyph_id(c));
+
+        assert!("0123456789".chars().all(|c| advance(c) == advance('0')));
+    }
+}
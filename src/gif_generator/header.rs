@@ -0,0 +1,70 @@
+use std::rc::Rc;
+
+use image::{Rgba, RgbaImage};
+
+use crate::data_fetcher::SessionData;
+
+use super::{Compositor, FontStyle, Rect, RenderConfig, TextCache};
+
+// Event, session and date of the compared laps, followed by an optional
+// subtitle. Lines without data are left out.
+pub struct Header {
+    lines: Vec<Rc<RgbaImage>>
+}
+
+pub type TextLine = (FontStyle, u32, Rgba<u8>, String);
+
+impl Header {
+    pub fn new(session: Option<&SessionData>, subtitle: Option<&str>, text_cache: &TextCache,
+        config: &RenderConfig) -> Header {
+        let lines = Self::get_lines(session, subtitle, config).into_iter()
+            .map(|(style, size, color, text)| text_cache.get_text(style, size, color, &text))
+            .collect();
+
+        Header { lines }
+    }
+
+    pub fn get_lines(session: Option<&SessionData>, subtitle: Option<&str>, config: &RenderConfig) -> Vec<TextLine> {
+        let mut lines = Vec::new();
+
+        if let Some(session) = session {
+            lines.push((FontStyle::Bold, config.driver_font_size, config.theme.accent(), 
+                format!("{} {}", session.year, session.event_name)));
+            lines.push((FontStyle::Regular, config.team_font_size, config.theme.foreground(), 
+                Self::get_details_str(session)));
+        }
+
+        if let Some(subtitle) = subtitle {
+            lines.push((FontStyle::Regular, config.team_font_size, config.theme.foreground(), 
+                String::from(subtitle)));
+        }
+
+        lines
+    }
+
+    fn get_details_str(session: &SessionData) -> String {
+        let mut details = vec![session.session_name.clone(), session.circuit_name.clone(), session.date.clone()];
+
+        if let Some(weather) = &session.weather {
+            details.push(format!("Air {:.0}°C", weather.air_temp));
+            details.push(format!("Track {:.0}°C", weather.track_temp));
+            if weather.rainfall {
+                details.push(String::from("Rain"));
+            }
+        }
+
+        details.join(" · ")
+    }
+
+    // Draws the lines centered in the rect.
+    pub fn compose(&self, compositor: &mut Compositor, rect: Rect) {
+        let (x, mut y) = rect.position();
+        let height: u32 = self.lines.iter().map(|line| line.height()).sum();
+        y += (rect.height as i64 - height as i64) / 2;
+
+        for line in &self.lines {
+            compositor.blend(line, x + (rect.width as i64 - line.width() as i64) / 2, y);
+            y += line.height() as i64;
+        }
+    }
+}
@@ -1,87 +1,101 @@
-use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
-use image::{imageops::overlay, Rgba, RgbaImage};
-use imageproc::{definitions::HasWhite, drawing::{draw_text_mut, text_size}};
+use image::Rgba;
 
 use crate::data_fetcher::CompleteDriverData;
 
-use super::{DRIVER_FONT_SIZE, DRIVER_STATS_HEIGHT, DRIVER_TEAM_MARGIN, 
-    LAP_SPEED_FONT_SIZE, NAME_LAP_SPEED_MARGIN, PADDING_LR, PADDING_TB, 
-    PADDING_TB_INNER, SECTOR_FONT_SIZE, SECTOR_TIMES_MARGIN, 
-    SIDEBAR_WIDTH, TEAM_FONT_SIZE, TRACK_HEIGHT, TRANSPARENT};
+use super::{compound_badge, lap_compound, tyre_age_str, Compositor, FontStyle, Rect, RenderConfig, TextCache};
 
+#[allow(clippy::upper_case_acronyms)]
 pub struct HUD<'a> {
     d1: &'a CompleteDriverData,
     d2: &'a CompleteDriverData,
 
-    regular_font: &'a FontRef<'a>,
-    bold_font: &'a FontRef<'a>
+    text_cache: &'a TextCache<'a>,
+    config: &'a RenderConfig
 }
 
 impl <'a> HUD<'a> {
-    pub fn new(d1: &'a CompleteDriverData, d2: &'a CompleteDriverData, regular_font: &'a FontRef<'a>, bold_font: &'a FontRef<'a>) -> HUD<'a> {
-        HUD { d1, d2, regular_font, bold_font }
+    pub fn new(d1: &'a CompleteDriverData, d2: &'a CompleteDriverData, text_cache: &'a TextCache<'a>, config: &'a RenderConfig) -> HUD<'a> {
+        HUD { d1, d2, text_cache, config }
     }
 
-    pub fn get_hud(&self, frame: usize) -> RgbaImage {
-        let mut combined_buffer = 
-            RgbaImage::from_pixel(SIDEBAR_WIDTH, TRACK_HEIGHT, TRANSPARENT);
-    
-        let d1_stats = self.get_driver_stats(&self.d1, frame);
-        let d2_stats = self.get_driver_stats(&self.d2, frame);
-    
-        overlay(&mut combined_buffer, &d1_stats, 0, PADDING_TB as i64);
-        overlay(&mut combined_buffer, &d2_stats, 0, (TRACK_HEIGHT - PADDING_TB - DRIVER_STATS_HEIGHT) as i64);
-        
-        combined_buffer
+    // Draws the stats block of the given driver (1 or 2) into the rect.
+    pub fn compose_driver_stats(&self, compositor: &mut Compositor, driver: u8, rect: Rect, frame: usize) {
+        let driver_data = if driver == 1 { self.d1 } else { self.d2 };
+
+        self.draw_driver_stats(compositor, driver_data, frame, rect);
     }
 
     fn has_finished(driver_data: &CompleteDriverData, current_frame: usize) -> bool {
         current_frame >= driver_data.telemetry.len()
     }
     
-    fn get_driver_and_team_name(&self, driver_name: &str, team_name: &str, color: Rgba<u8>) -> RgbaImage {
-        let driver_font = self.bold_font;
-        let team_font = self.regular_font;
-        
-        let driver_font_scale = driver_font.pt_to_px_scale(DRIVER_FONT_SIZE as f32)
-            .unwrap_or(PxScale::from(DRIVER_FONT_SIZE as f32));
-        let driver_height = driver_font.as_scaled(driver_font_scale).height().ceil() as u32;
-    
-        let team_font_scale = team_font.pt_to_px_scale(TEAM_FONT_SIZE as f32)
-            .unwrap_or(PxScale::from(TEAM_FONT_SIZE as f32));
-        let team_height = team_font.as_scaled(team_font_scale).height().ceil() as u32;
-    
-        let mut name_buffer = RgbaImage::from_pixel(SIDEBAR_WIDTH, 
-            driver_height + team_height, TRANSPARENT);
+    // Returns the height of the drawn block.
+    fn draw_driver_and_team_name(&self, compositor: &mut Compositor, x: i64, y: i64, 
+        driver_data: &CompleteDriverData) -> u32 {
+        let driver_height = self.text_cache.line_height(FontStyle::Bold, self.config.driver_font_size);
+        let team_height = self.text_cache.line_height(FontStyle::Regular, self.config.team_font_size);
+
+        let driver_text = self.text_cache.get_text(FontStyle::Bold, self.config.driver_font_size, 
+            Rgba::from(driver_data.driver.team_color), &driver_data.driver.broadcast_name);
+        let team_text = self.text_cache.get_text(FontStyle::Regular, self.config.team_font_size, 
+            self.config.theme.foreground(), &driver_data.driver.team_name);
+        let team_y = y + driver_height as i64 + self.config.driver_team_margin as i64;
     
-        draw_text_mut(&mut name_buffer, color, PADDING_LR as i32, 0, 
-            driver_font_scale, driver_font, driver_name);
-        draw_text_mut(&mut name_buffer, Rgba::white(), PADDING_LR as i32, 
-            driver_height as i32 + DRIVER_TEAM_MARGIN, team_font_scale, team_font, team_name);
+        compositor.blend(&driver_text, x + self.config.padding_lr as i64, y);
+        compositor.blend(&team_text, x + self.config.padding_lr as i64, team_y);
+        self.draw_tyre_info(compositor, x + self.config.padding_lr as i64, team_y, driver_data);
     
-        name_buffer
+        driver_height + team_height
+    }
+
+    /// Offsets of the compound badge and the tyre age from the start of the
+    /// team name, which they follow on its line.
+    pub fn tyre_info_offsets(&self, team_name: &str) -> (u32, u32) {
+        let size = self.config.team_font_size;
+        let gap = self.text_cache.line_height(FontStyle::Regular, size) / 2;
+        let badge_x = self.text_cache.get_text(FontStyle::Regular, size, self.config.theme.foreground(), team_name)
+            .width() + gap;
+
+        (badge_x, badge_x + self.text_cache.line_height(FontStyle::Regular, size) + gap / 2)
+    }
+
+    // Draws the compound badge and the tyre age after the team name, if the
+    // lap has them.
+    fn draw_tyre_info(&self, compositor: &mut Compositor, x: i64, y: i64, driver_data: &CompleteDriverData) {
+        if !self.config.tyre_info {
+            return;
+        }
+
+        let size = self.config.team_font_size;
+        let (badge_x, age_x) = self.tyre_info_offsets(&driver_data.driver.team_name);
+        let age_x = match lap_compound(&driver_data.lap) {
+            Some(compound) => {
+                compositor.blend(&compound_badge(compound, self.text_cache, self.config, size), x + badge_x as i64, y);
+                age_x
+            },
+            None => badge_x
+        };
+
+        if let Some(age) = tyre_age_str(&driver_data.lap) {
+            let age_text = self.text_cache.get_text(FontStyle::Regular, size, self.config.theme.foreground(), &age);
+            compositor.blend(&age_text, x + age_x as i64, y);
+        }
+    }
+
+    fn draw_centered_text(&self, compositor: &mut Compositor, x: i64, y: i64, width: u32, text: &str) {
+        let text_buffer = self.text_cache.get_text(FontStyle::Bold, self.config.lap_speed_font_size, 
+            self.config.theme.foreground(), text);
+
+        let dx = width.saturating_sub(text_buffer.width()) / 2;
+
+        compositor.blend(&text_buffer, x + dx as i64, y);
     }
     
-    fn get_speed(&self, driver_data: &CompleteDriverData, current_frame: usize) -> RgbaImage {
-        let font_scale = self.bold_font.pt_to_px_scale(LAP_SPEED_FONT_SIZE as f32)
-            .unwrap_or(PxScale::from(LAP_SPEED_FONT_SIZE as f32));
-        let height = self.bold_font.as_scaled(font_scale).height().ceil() as u32;
-    
-        let speed = driver_data.telemetry[current_frame].speed;
-        let speed_str = format!("{} km/h", speed);
-    
-        let (x, _) = text_size(font_scale, self.bold_font, &speed_str);
-        let dx = (SIDEBAR_WIDTH - x) / 2;
-    
-        let mut speed_buffer = 
-            RgbaImage::from_pixel(SIDEBAR_WIDTH, height, TRANSPARENT);
-        draw_text_mut(&mut speed_buffer, Rgba::white(), 
-            dx as i32, 0, font_scale, self.bold_font, &speed_str);
-    
-        speed_buffer
+    fn get_speed_str(&self, driver_data: &CompleteDriverData, current_frame: usize) -> String {
+        self.config.units.format_speed(driver_data.telemetry[current_frame].speed)
     }
     
-    fn get_str_time(time: i32) -> String {
+    pub fn get_str_time(time: i32) -> String {
         let minutes = time / 60000;
         let seconds = (time / 1000) % 60;
         let miliseconds = time % 1000;
@@ -89,32 +103,14 @@ impl <'a> HUD<'a> {
         format!("{}:{:0>2}.{:0>3}", minutes, seconds, miliseconds)
     }
     
-    fn get_time(&self, time: i32) -> RgbaImage {
-        let font_scale: PxScale = self.bold_font.pt_to_px_scale(LAP_SPEED_FONT_SIZE as f32)
-            .unwrap_or(PxScale::from(LAP_SPEED_FONT_SIZE as f32));
-        let height = self.bold_font.as_scaled(font_scale).height().ceil() as u32;
-    
-        let time_str = Self::get_str_time(time);
-    
-        let (x, _) = text_size(font_scale, self.bold_font, &time_str);
-        let dx = (SIDEBAR_WIDTH - x) / 2;
-    
-        let mut time_buffer = 
-            RgbaImage::from_pixel(SIDEBAR_WIDTH, height, TRANSPARENT);
-        draw_text_mut(&mut time_buffer, Rgba::white(), 
-            dx as i32, 0, font_scale, self.bold_font, &time_str);
-    
-        time_buffer
-    }
-    
-    fn time_to_sector_time_str(time: i32) -> String {
+    pub fn time_to_sector_time_str(time: i32) -> String {
         let seconds = (time / 1000) % 60;
         let miliseconds = time % 1000;
     
         format!("{:0>2}.{:0>3}", seconds, miliseconds)
     }
     
-    fn get_sector_time_str(driver_data: &CompleteDriverData, sector: u8, current_frame: usize) -> String {
+    pub fn get_sector_time_str(driver_data: &CompleteDriverData, sector: u8, current_frame: usize) -> String {
         let sector_session_time = match sector {
             1 => driver_data.lap.sector1_session_time,
             2 => driver_data.lap.sector2_session_time,
@@ -136,48 +132,42 @@ impl <'a> HUD<'a> {
         }
     }
     
-    fn get_sector_times(&self, driver_data: &CompleteDriverData, current_frame: usize) -> RgbaImage {
-        let font_scale = self.regular_font.pt_to_px_scale(SECTOR_FONT_SIZE as f32)
-            .unwrap_or(PxScale::from(SECTOR_FONT_SIZE as f32));
-        let height = self.regular_font.as_scaled(font_scale).height().ceil() as u32;
-    
-        let mut sector_times_buffer = 
-            RgbaImage::from_pixel(SIDEBAR_WIDTH, 3 * height, TRANSPARENT);
+    fn draw_sector_times(&self, compositor: &mut Compositor, x: i64, y: i64, 
+        driver_data: &CompleteDriverData, current_frame: usize) {
+        let height = self.text_cache.line_height(FontStyle::Timing, self.config.sector_font_size);
     
         for i in 0..3 {
-            let mut sector_time_buffer = 
-                RgbaImage::from_pixel(SIDEBAR_WIDTH, height, TRANSPARENT);
-            let sector_time_str = Self::get_sector_time_str(&driver_data, i + 1, current_frame);
-
-            draw_text_mut(&mut sector_time_buffer, Rgba::white(), 
-                PADDING_LR as i32, 0, font_scale, self.regular_font, &sector_time_str);
-            overlay(&mut sector_times_buffer, &sector_time_buffer, 
-                0, i as i64 * height as i64);
+            let sector_time_str = Self::get_sector_time_str(driver_data, i + 1, current_frame);
+            if sector_time_str.is_empty() {
+                continue;
+            }
+
+            let sector_time_text = self.text_cache.get_text(FontStyle::Timing, self.config.sector_font_size, 
+                self.config.theme.foreground(), &sector_time_str);
+            compositor.blend(&sector_time_text, 
+                x + self.config.padding_lr as i64, y + i as i64 * height as i64);
         }
-    
-        sector_times_buffer
     }
     
-    fn get_driver_stats(&self, driver_data: &CompleteDriverData, current_frame: usize) -> RgbaImage {
-        let mut stats = 
-            RgbaImage::from_pixel(SIDEBAR_WIDTH, DRIVER_STATS_HEIGHT, TRANSPARENT);
-    
-        let driver_name_buffer = self.get_driver_and_team_name(&driver_data.driver.broadcast_name, 
-            &driver_data.driver.team_name, Rgba::from(driver_data.driver.team_color));
+    fn draw_driver_stats(&self, compositor: &mut Compositor, driver_data: &CompleteDriverData, 
+        current_frame: usize, rect: Rect) {
+        let (x, y) = rect.position();
+
+        let name_height = self.draw_driver_and_team_name(compositor, x, y + self.config.padding_tb_inner as i64, 
+            driver_data);
     
         let time_or_speed = if Self::has_finished(driver_data, current_frame) {
-            self.get_time(driver_data.lap.lap_time)
+            Self::get_str_time(driver_data.lap.lap_time)
         } else {
-            self.get_speed(driver_data, current_frame)
+            self.get_speed_str(driver_data, current_frame)
         };
-        let sector_times = self.get_sector_times(driver_data, current_frame);
-    
-        overlay(&mut stats, &driver_name_buffer, 0, (PADDING_TB_INNER) as i64);
-        overlay(&mut stats, &time_or_speed, 
-            0, (PADDING_TB_INNER + driver_name_buffer.height() + NAME_LAP_SPEED_MARGIN) as i64);
-        overlay(&mut stats, &sector_times, 
-            0, (DRIVER_STATS_HEIGHT - sector_times.height() - SECTOR_TIMES_MARGIN) as i64);
-    
-        stats
+        self.draw_centered_text(compositor, x, 
+            y + (self.config.padding_tb_inner + name_height + self.config.name_lap_speed_margin) as i64, 
+            rect.width, &time_or_speed);
+
+        let sector_times_height = 3 * self.text_cache.line_height(FontStyle::Timing, self.config.sector_font_size);
+        self.draw_sector_times(compositor, x, 
+            y + rect.height as i64 - sector_times_height as i64 - self.config.sector_times_margin as i64, 
+            driver_data, current_frame);
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,7 @@
 use std::cmp::{min, max};
 use crate::data_fetcher::DriverTelemetryData;
 
-pub fn find_extrema(d1: &Vec<DriverTelemetryData>, d2: &Vec<DriverTelemetryData>) -> ((i32, i32), (i32, i32)) {
+pub fn find_extrema(d1: &[DriverTelemetryData], d2: &[DriverTelemetryData]) -> ((i32, i32), (i32, i32)) {
     let min_x_d1 = d1.iter().map(|s| s.x).min().expect("Invalid data");
     let min_x_d2 = d2.iter().map(|s| s.x).min().expect("Invalid data");
 
@@ -21,7 +21,7 @@ pub fn find_extrema(d1: &Vec<DriverTelemetryData>, d2: &Vec<DriverTelemetryData>
 }
 
 pub fn resize_data_to_dims(d1: &mut Vec<DriverTelemetryData>, d2: &mut Vec<DriverTelemetryData>, width: u32, height: u32) {
-    let (mut range_x, mut range_y) = find_extrema(&d1, &d2);
+    let (mut range_x, mut range_y) = find_extrema(d1, d2);
 
     if range_x.0 < 0 {
         let dx = range_x.0.abs();
@@ -86,4 +86,20 @@ pub fn center_data_to_dims(d1: &mut Vec<DriverTelemetryData>, d2: &mut Vec<Drive
         pos.x += dx;
         pos.y += dy;
     }
-}
\ No newline at end of file
+}
+
+// Moves the data to the origin and scales it up or down to fill the dimensions,
+// so that a part of the track is shown as large as the whole track would be.
+pub fn fit_data_to_dims(d1: &mut [DriverTelemetryData], d2: &mut [DriverTelemetryData], width: u32, height: u32) {
+    let (range_x, range_y) = find_extrema(d1, d2);
+
+    let dx = (range_x.1 - range_x.0).max(1) as f32 / height as f32;
+    let dy = (range_y.1 - range_y.0).max(1) as f32 / width as f32;
+
+    let ratio = dx.max(dy);
+
+    for pos in d1.iter_mut().chain(d2.iter_mut()) {
+        pos.x = ((pos.x - range_x.0) as f32 / ratio).round() as i32;
+        pos.y = ((pos.y - range_y.0) as f32 / ratio).round() as i32;
+    }
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_fetcher::{CompleteDriverData, CornerData, DriverTelemetryData, LapData};
+
+use super::RenderConfig;
+
+/// Part of the lap to render, given either by relative distance (`start` and
+/// `end` between 0 and 1), by the first and the last sector (`sectors`) or
+/// by distance from the start of the lap in metres (`distance`).
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LapPortion {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sectors: Option<(u8, u8)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<(f64, f64)>
+}
+
+impl LapPortion {
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.start, self.end, self.sectors, self.distance) {
+            (Some(start), Some(end), None, None) if (0.0..=1.0).contains(&start) && (0.0..=1.0).contains(&end)
+                && start < end => Ok(()),
+            (None, None, Some((first, last)), None) if (1..=3).contains(&first) && (1..=3).contains(&last)
+                && first <= last => Ok(()),
+            (None, None, None, Some((start, end))) if start >= 0.0 && start < end => Ok(()),
+            _ => Err(String::from("Lap portion must have either start < end between 0 and 1, sectors [first, last] \
+                between 1 and 3, or distance [start, end] in metres"))
+        }
+    }
+
+    // Session times at which the driver entered the first and left the last sector.
+    fn sector_session_times(lap: &LapData, (first, last): (u8, u8)) -> (i64, i64) {
+        let sector_end = |sector: u8| match sector {
+            1 => lap.sector1_session_time,
+            2 => lap.sector2_session_time,
+            _ => lap.sector3_session_time
+        };
+        let start = match first {
+            1 => lap.sector1_session_time - lap.sector1_time as i64,
+            _ => sector_end(first - 1)
+        };
+
+        (start, sector_end(last))
+    }
+
+    /// Removes the driver's telemetry outside of the portion. Sectors are
+    /// cut at the driver's own sector times and distances at the driver's own
+    /// distance driven.
+    pub fn crop(&self, driver_data: &mut CompleteDriverData) -> Result<(), String> {
+        match (self.start, self.end, self.sectors, self.distance) {
+            (Some(start), Some(end), _, _) =>
+                driver_data.telemetry.retain(|t| (start..=end).contains(&t.relative_distance)),
+            (_, _, Some(sectors), _) => {
+                let (start, end) = Self::sector_session_times(&driver_data.lap, sectors);
+                driver_data.telemetry.retain(|t| (start..=end).contains(&t.session_time));
+            },
+            (_, _, _, Some((start, end))) => {
+                if !has_distances(driver_data) {
+                    return Err(String::from("Lap portion by distance requires distance telemetry, fetch the laps again"));
+                }
+                driver_data.telemetry.retain(|t| t.distance.is_some_and(|distance| (start..=end).contains(&distance)));
+            },
+            _ => return Err(String::from("Lap portion has no start and end"))
+        }
+
+        if driver_data.telemetry.len() < 2 {
+            return Err(format!("Lap portion has too little telemetry of {}", driver_data.driver.broadcast_name));
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether every sample of the driver's telemetry has its distance in metres.
+pub fn has_distances(driver_data: &CompleteDriverData) -> bool {
+    driver_data.telemetry.iter().all(|t| t.distance.is_some())
+}
+
+/// Part of the lap spanned by the x-axis of the plots: the whole lap, or
+/// with a lap portion, the part of the lap both drivers' telemetry covers.
+/// Distances are in metres if both laps have them, otherwise relative.
+#[derive(Clone, Copy, Debug)]
+pub struct DistanceRange {
+    pub start: f64,
+    pub end: f64,
+    pub metres: bool
+}
+
+impl DistanceRange {
+    pub fn new(config: &RenderConfig, d1: &CompleteDriverData, d2: &CompleteDriverData) -> DistanceRange {
+        let metres = has_distances(d1) && has_distances(d2);
+        let distance = |t: &DriverTelemetryData| if metres { t.distance.unwrap_or(0.0) } else { t.relative_distance };
+
+        let (start, end) = [d1, d2].iter().flat_map(|driver_data| &driver_data.telemetry)
+            .fold((f64::MAX, f64::MIN), |(start, end), t| (start.min(distance(t)), end.max(distance(t))));
+
+        let (start, end) = match (&config.lap_portion, metres) {
+            (Some(_), _) => (start, end),
+            (None, true) => (0.0, end),
+            (None, false) => (0.0, 1.0)
+        };
+
+        DistanceRange { start, end: end.max(start + f64::EPSILON), metres }
+    }
+
+    fn ratio(&self, distance: f64) -> f64 {
+        (distance - self.start) / (self.end - self.start)
+    }
+
+    /// Position of the sample along the x-axis, from 0 at its start to 1 at its end.
+    pub fn position(&self, t: &DriverTelemetryData) -> f64 {
+        self.ratio(if self.metres { t.distance.unwrap_or(0.0) } else { t.relative_distance })
+    }
+
+    /// Position of the corner along the x-axis, if it lies within the range.
+    pub fn corner_position(&self, corner: &CornerData) -> Option<f64> {
+        let distance = if self.metres { corner.distance? } else { corner.relative_distance };
+        (self.start..=self.end).contains(&distance).then(|| self.ratio(distance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_generator::test_utils::{lap, SAMPLE_SPACING, SESSION_START};
+
+    fn portion(start: Option<f64>, end: Option<f64>, sectors: Option<(u8, u8)>, distance: Option<(f64, f64)>) -> LapPortion {
+        LapPortion { start, end, sectors, distance }
+    }
+
+    fn cropped(portion: &LapPortion) -> Result<Vec<i64>, String> {
+        let mut driver_data = lap(&[200; 11], 50);
+        portion.crop(&mut driver_data)?;
+
+        Ok(driver_data.telemetry.iter().map(|t| (t.session_time - SESSION_START) / 50).collect())
+    }
+
+    #[test]
+    fn crops_to_relative_distance() {
+        assert_eq!(cropped(&portion(Some(0.2), Some(0.5), None, None)), Ok(vec![2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn crops_to_sector_times() {
+        // Sectors of the 500 ms lap end after 166, 332 and 500 ms.
+        assert_eq!(cropped(&portion(None, None, Some((2, 2)), None)), Ok(vec![4, 5, 6]));
+        assert_eq!(cropped(&portion(None, None, Some((1, 3)), None)), Ok((0..=10).collect()));
+    }
+
+    #[test]
+    fn crops_to_distance_in_metres() {
+        let distance = (2.0 * SAMPLE_SPACING, 5.0 * SAMPLE_SPACING);
+
+        assert_eq!(cropped(&portion(None, None, None, Some(distance))), Ok(vec![2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn distance_requires_distance_telemetry() {
+        let mut driver_data = lap(&[200; 11], 50);
+        driver_data.telemetry[3].distance = None;
+
+        let error = portion(None, None, None, Some((0.0, 50.0))).crop(&mut driver_data).unwrap_err();
+
+        assert_eq!(error, "Lap portion by distance requires distance telemetry, fetch the laps again");
+    }
+
+    #[test]
+    fn rejects_portions_with_too_little_telemetry() {
+        assert!(cropped(&portion(Some(0.91), Some(0.99), None, None)).unwrap_err().starts_with("Lap portion has too little telemetry"));
+    }
+
+    #[test]
+    fn validates_portions() {
+        assert!(portion(Some(0.2), Some(0.5), None, None).validate().is_ok());
+        assert!(portion(None, None, Some((2, 3)), None).validate().is_ok());
+        assert!(portion(None, None, None, Some((100.0, 200.0))).validate().is_ok());
+
+        for invalid in [portion(Some(0.5), Some(0.2), None, None), portion(Some(0.2), None, None, None),
+            portion(None, None, Some((3, 2)), None), portion(None, None, Some((0, 2)), None),
+            portion(None, None, None, Some((200.0, 100.0))), portion(Some(0.2), Some(0.5), Some((1, 2)), None),
+            portion(None, None, None, None)] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Orientation, RenderConfig};
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    pub fn position(&self) -> (i64, i64) {
+        (self.x as i64, self.y as i64)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+/// Divides the canvas into equally sized cells for grid placement.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Grid {
+    pub columns: u32,
+    pub rows: u32
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct GridCell {
+    pub column: u32,
+    pub row: u32,
+    #[serde(default = "one")]
+    pub column_span: u32,
+    #[serde(default = "one")]
+    pub row_span: u32
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PanelKind {
+    TrackMap,
+    FollowCamera,
+    SpeedPlot,
+    DeltaPlot,
+    DriverStats,
+    Title,
+    Header
+}
+
+/// A panel and where to place it, either as a rectangle in pixels
+/// or as a cell (with optional spans) of the layout's grid.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PanelSpec {
+    pub kind: PanelKind,
+    /// Driver (1 or 2) whose stats are shown, for `driver_stats` panels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver: Option<u8>,
+    /// Text of `title` panels, which defaults to "<driver1> vs <driver2>",
+    /// or the subtitle of `header` panels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rect: Option<Rect>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid: Option<GridCell>
+}
+
+impl PanelSpec {
+    fn new(kind: PanelKind, rect: Rect) -> PanelSpec {
+        PanelSpec { kind, driver: None, text: None, rect: Some(rect), grid: None }
+    }
+
+    fn driver_stats(driver: u8, rect: Rect) -> PanelSpec {
+        PanelSpec { driver: Some(driver), ..PanelSpec::new(PanelKind::DriverStats, rect) }
+    }
+}
+
+/// Declares the canvas size and the panels composed into every frame,
+/// in drawing order.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Layout {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grid: Option<Grid>,
+    pub panels: Vec<PanelSpec>
+}
+
+impl Layout {
+    /// Loads a layout from a JSON file.
+    pub fn from_file(path: &str) -> Result<Layout, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read layout file {}: {}", path, e))?;
+        let layout: Layout = serde_json::from_str(&json)
+            .map_err(|e| format!("Unable to parse layout file {}: {}", path, e))?;
+
+        Ok(layout)
+    }
+
+    /// Returns the built-in arrangement of the config's orientation.
+    pub fn from_config(config: &RenderConfig) -> Layout {
+        Self::built_in(config, false)
+    }
+
+    /// Returns the built-in arrangement of the config's orientation with a
+    /// header across the top, if the config shows one.
+    pub fn from_config_with_header(config: &RenderConfig) -> Layout {
+        Self::built_in(config, config.header)
+    }
+
+    fn built_in(config: &RenderConfig, header: bool) -> Layout {
+        let header_height = if header { config.header_height } else { 0 };
+        let stats_top = config.driver_stats_top(header);
+        let track_map = Rect::new(0, header_height, config.track_width, config.track_height - header_height);
+
+        let mut panels = match config.orientation {
+            Orientation::Portrait => vec![
+                PanelSpec::new(PanelKind::TrackMap, track_map),
+                PanelSpec::new(PanelKind::SpeedPlot,
+                    Rect::new(0, config.track_height, config.telemetry_panel_width(), config.telemetry_height)),
+                PanelSpec::driver_stats(1,
+                    Rect::new(config.track_width, stats_top,
+                        config.sidebar_width, config.driver_stats_height)),
+                PanelSpec::driver_stats(2,
+                    Rect::new(config.track_width, config.track_height - config.padding_tb - config.driver_stats_height,
+                        config.sidebar_width, config.driver_stats_height))
+            ],
+            Orientation::Landscape => {
+                let header_offset = stats_top - config.padding_tb;
+
+                vec![
+                    PanelSpec::new(PanelKind::TrackMap, track_map),
+                    PanelSpec::new(PanelKind::SpeedPlot,
+                        Rect::new(config.track_width, config.hud_stats_height() + header_offset,
+                            config.telemetry_panel_width(), config.telemetry_height - header_offset)),
+                    PanelSpec::driver_stats(1,
+                        Rect::new(config.track_width, stats_top,
+                            config.sidebar_width, config.driver_stats_height)),
+                    PanelSpec::driver_stats(2,
+                        Rect::new(config.track_width + config.sidebar_width, stats_top,
+                            config.sidebar_width, config.driver_stats_height))
+                ]
+            }
+        };
+
+        if header {
+            panels.push(PanelSpec::new(PanelKind::Header, Rect::new(0, 0, config.gif_width(), header_height)));
+        }
+
+        Layout { width: config.gif_width(), height: config.gif_height(), grid: None, panels }
+    }
+
+    fn grid_cell_rect(&self, cell: &GridCell) -> Result<Rect, String> {
+        let grid = self.grid
+            .ok_or_else(|| String::from("Layout uses grid placement but declares no grid"))?;
+
+        if grid.columns == 0 || grid.rows == 0 {
+            return Err(String::from("Layout grid must have at least one column and one row"));
+        }
+
+        let fits = |start: u32, span: u32, cells: u32| span > 0 && start.checked_add(span).is_some_and(|end| end <= cells);
+        if !fits(cell.column, cell.column_span, grid.columns) || !fits(cell.row, cell.row_span, grid.rows) {
+            return Err(format!("Grid cell at column {}, row {} does not fit into the {}x{} grid",
+                cell.column, cell.row, grid.columns, grid.rows));
+        }
+
+        let column_x = |column: u32| (column as u64 * self.width as u64 / grid.columns as u64) as u32;
+        let row_y = |row: u32| (row as u64 * self.height as u64 / grid.rows as u64) as u32;
+
+        let x = column_x(cell.column);
+        let y = row_y(cell.row);
+
+        Ok(Rect::new(x, y, column_x(cell.column + cell.column_span) - x, row_y(cell.row + cell.row_span) - y))
+    }
+
+    fn panel_rect(&self, panel: &PanelSpec) -> Result<Rect, String> {
+        let rect = match (&panel.rect, &panel.grid) {
+            (Some(rect), None) => *rect,
+            (None, Some(cell)) => self.grid_cell_rect(cell)?,
+            _ => return Err(format!("Panel {:?} must have exactly one of rect or grid", panel.kind))
+        };
+
+        let fits = |start: u32, size: u32, canvas: u32| size > 0 && start.checked_add(size).is_some_and(|end| end <= canvas);
+        if !fits(rect.x, rect.width, self.width) || !fits(rect.y, rect.height, self.height) {
+            return Err(format!("Panel {:?} does not fit into the {}x{} canvas",
+                panel.kind, self.width, self.height));
+        }
+
+        Ok(rect)
+    }
+
+    /// Resolves the placement of every panel and checks that the layout
+    /// can be rendered with the given config.
+    pub fn resolve(&self, config: &RenderConfig) -> Result<Vec<(PanelSpec, Rect)>, String> {
+        if self.width == 0 || self.height == 0 || self.width > u16::MAX as u32 || self.height > u16::MAX as u32 {
+            return Err(String::from("Layout dimensions must be between 1 and 65535 pixels"));
+        }
+
+        let mut resolved = Vec::new();
+        for panel in &self.panels {
+            let rect = self.panel_rect(panel)?;
+
+            match panel.kind {
+                PanelKind::TrackMap if rect.width <= 2 * config.padding || rect.height <= 2 * config.padding =>
+                    return Err(String::from("Track map panel must be larger than twice the padding")),
+                PanelKind::FollowCamera if rect.width <= 2 * config.padding || rect.height <= 2 * config.padding =>
+                    return Err(String::from("Follow camera panel must be larger than twice the padding")),
+                PanelKind::SpeedPlot | PanelKind::DeltaPlot
+                    if rect.width <= 2 * config.padding + config.telemetry_plot_axes_labels_margin
+                    || rect.height <= 2 * config.padding + config.telemetry_plot_axes_labels_margin =>
+                    return Err(format!("Panel {:?} is too small for its padding and axes labels", panel.kind)),
+                PanelKind::DriverStats if !matches!(panel.driver, Some(1) | Some(2)) =>
+                    return Err(String::from("Driver stats panels must set driver to 1 or 2")),
+                _ => ()
+            }
+
+            resolved.push((panel.clone(), rect));
+        }
+
+        let no_track_maps = self.panels.iter().filter(|p| p.kind == PanelKind::TrackMap).count();
+        if no_track_maps > 1 {
+            return Err(String::from("Layout may contain at most one track map panel"));
+        }
+
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(grid: Option<Grid>, panels: Vec<PanelSpec>) -> Layout {
+        Layout { width: 800, height: 600, grid, panels }
+    }
+
+    fn grid_panel(kind: PanelKind, column: u32, row: u32, column_span: u32, row_span: u32) -> PanelSpec {
+        PanelSpec { kind, driver: None, text: None, rect: None, grid: Some(GridCell { column, row, column_span, row_span }) }
+    }
+
+    #[test]
+    fn built_in_layouts_resolve() {
+        for config in [RenderConfig::default(), RenderConfig::for_resolution(1280, 720)] {
+            let layout = Layout::from_config(&config);
+
+            assert_eq!(layout.resolve(&config).unwrap().len(), layout.panels.len());
+        }
+    }
+
+    #[test]
+    fn built_in_layouts_fit_below_the_header() {
+        for (width, height) in [(768, 768), (1280, 720), (720, 1280), (256, 256), (1000, 300)] {
+            let config = RenderConfig::for_resolution(width, height);
+            let resolved = Layout::from_config_with_header(&config).resolve(&config).unwrap();
+
+            let (header, rect) = resolved.last().unwrap();
+            assert_eq!(header.kind, PanelKind::Header);
+            assert_eq!(*rect, Rect::new(0, 0, width, config.header_height));
+
+            let rects: Vec<Rect> = resolved.iter().map(|(_, rect)| *rect).collect();
+            for (i, a) in rects.iter().enumerate() {
+                for b in &rects[i + 1..] {
+                    let overlaps = a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height;
+                    assert!(!overlaps, "{}x{}: {:?} overlaps {:?}", width, height, a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn header_can_be_turned_off() {
+        let config = RenderConfig { header: false, ..RenderConfig::default() };
+
+        assert_eq!(Layout::from_config_with_header(&config), Layout::from_config(&config));
+    }
+
+    #[test]
+    fn resolves_grid_cells() {
+        let layout = layout(Some(Grid { columns: 3, rows: 2 }), vec![
+            grid_panel(PanelKind::TrackMap, 0, 0, 2, 2),
+            grid_panel(PanelKind::Title, 2, 1, 1, 1)
+        ]);
+
+        let rects: Vec<Rect> = layout.resolve(&RenderConfig::default()).unwrap().into_iter().map(|(_, rect)| rect).collect();
+
+        assert_eq!(rects, vec![Rect::new(0, 0, 533, 600), Rect::new(533, 300, 267, 300)]);
+    }
+
+    #[test]
+    fn rejects_panels_outside_of_the_canvas() {
+        let config = RenderConfig::default();
+
+        for rect in [Rect::new(400, 0, 401, 100), Rect::new(0, 0, 0, 100), Rect::new(u32::MAX - 100, 0, 500, 100),
+            Rect::new(0, u32::MAX, 100, u32::MAX)] {
+            let layout = layout(None, vec![PanelSpec::new(PanelKind::Title, rect)]);
+
+            assert_eq!(layout.resolve(&config).unwrap_err(), "Panel Title does not fit into the 800x600 canvas");
+        }
+    }
+
+    #[test]
+    fn rejects_cells_outside_of_the_grid() {
+        let config = RenderConfig::default();
+
+        for panel in [grid_panel(PanelKind::Title, 1, 0, 2, 1), grid_panel(PanelKind::Title, 0, 0, 0, 1),
+            grid_panel(PanelKind::Title, u32::MAX, 0, 1, 1), grid_panel(PanelKind::Title, 0, 1, 1, u32::MAX)] {
+            let layout = layout(Some(Grid { columns: 2, rows: 2 }), vec![panel]);
+
+            assert!(layout.resolve(&config).unwrap_err().contains("does not fit into the 2x2 grid"));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_layouts() {
+        let config = RenderConfig::default();
+        let rect = Rect::new(0, 0, 400, 300);
+
+        let no_grid = layout(None, vec![grid_panel(PanelKind::Title, 0, 0, 1, 1)]);
+        assert_eq!(no_grid.resolve(&config).unwrap_err(), "Layout uses grid placement but declares no grid");
+
+        let no_driver = layout(None, vec![PanelSpec::new(PanelKind::DriverStats, rect)]);
+        assert_eq!(no_driver.resolve(&config).unwrap_err(), "Driver stats panels must set driver to 1 or 2");
+
+        let two_track_maps = layout(None, vec![PanelSpec::new(PanelKind::TrackMap, rect); 2]);
+        assert_eq!(two_track_maps.resolve(&config).unwrap_err(), "Layout may contain at most one track map panel");
+
+        let too_small = layout(None, vec![PanelSpec::new(PanelKind::SpeedPlot, Rect::new(0, 0, 60, 60))]);
+        assert!(too_small.resolve(&config).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use super::{Compositor, DeltaPlot, FollowCamera, Header, Rect, TelemetryPlot, Title, TrackMap, HUD};
+
+pub enum Panel<'a> {
+    TrackMap(TrackMap<'a>),
+    FollowCamera(FollowCamera<'a>),
+    SpeedPlot(TelemetryPlot<'a>),
+    DeltaPlot(DeltaPlot<'a>),
+    DriverStats(u8),
+    Title(Title),
+    Header(Header)
+}
+
+// A panel together with the rect it is composed into.
+pub struct PlacedPanel<'a> {
+    pub panel: Panel<'a>,
+    pub rect: Rect
+}
+
+impl <'a> PlacedPanel<'a> {
+    pub fn draw_next_frame(&mut self) {
+        match &mut self.panel {
+            Panel::TrackMap(track_map) => track_map.draw_next_frame(),
+            Panel::FollowCamera(follow_camera) => follow_camera.draw_next_frame(),
+            Panel::SpeedPlot(telemetry_plot) => telemetry_plot.draw_next_frame(),
+            Panel::DeltaPlot(delta_plot) => delta_plot.draw_next_frame(),
+            Panel::DriverStats(_) | Panel::Title(_) | Panel::Header(_) => ()
+        }
+    }
+
+    pub fn compose(&self, compositor: &mut Compositor, hud: &HUD, frame: usize) {
+        let (x, y) = self.rect.position();
+
+        match &self.panel {
+            Panel::TrackMap(track_map) => track_map.compose(compositor, x, y),
+            Panel::FollowCamera(follow_camera) => follow_camera.compose(compositor, self.rect),
+            Panel::SpeedPlot(telemetry_plot) => telemetry_plot.compose(compositor, x, y),
+            Panel::DeltaPlot(delta_plot) => delta_plot.compose(compositor, x, y),
+            Panel::DriverStats(driver) => hud.compose_driver_stats(compositor, *driver, self.rect, frame),
+            Panel::Title(title) => title.compose(compositor, self.rect),
+            Panel::Header(header) => header.compose(compositor, self.rect)
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_fetcher::{CompleteDriverData, DriverTelemetryData, SessionData};
+
+use super::has_distances;
+
+/// Highest output framerate. GIF delays are whole centiseconds and most
+/// viewers slow down delays shorter than 20 ms.
+pub const MAX_OUTPUT_FRAMERATE: u32 = 50;
+
+// Part of the lap before the first and after the last corner of a range
+// given by corners, as relative distance (about 50 m on a 5 km lap).
+const CORNER_MARGIN: f64 = 0.01;
+
+/// A part of the lap played at a different speed, given either by relative
+/// distance (`start` and `end` between 0 and 1), by the first and the last
+/// corner number (`corners`), which requires corner data of the session, or
+/// by distance from the start of the lap in metres (`distance`).
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SlowMotion {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corners: Option<(u32, u32)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<(f64, f64)>,
+    /// Multiplies the playback speed while either driver is in the range.
+    pub speed: f32
+}
+
+impl SlowMotion {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.speed.is_finite() || self.speed <= 0.0 {
+            return Err(String::from("Slow motion speed must be positive"));
+        }
+
+        match (self.start, self.end, self.corners, self.distance) {
+            (Some(start), Some(end), None, None) if (0.0..=1.0).contains(&start) && (0.0..=1.0).contains(&end)
+                && start < end => Ok(()),
+            (None, None, Some((first, last)), None) if first <= last => Ok(()),
+            (None, None, None, Some((start, end))) if start >= 0.0 && start < end => Ok(()),
+            _ => Err(String::from("Slow motion range must have either start < end between 0 and 1, \
+                corners [first, last] or distance [start, end] in metres"))
+        }
+    }
+
+    fn resolve(&self, session: Option<&SessionData>) -> Result<Range, String> {
+        let (first, last) = match (self.start, self.end, self.corners, self.distance) {
+            (Some(start), Some(end), _, _) => return Ok(Range::Relative(start, end)),
+            (_, _, Some(corners), _) => corners,
+            (_, _, _, Some((start, end))) => return Ok(Range::Metres(start, end)),
+            _ => return Err(String::from("Slow motion range has no start and end"))
+        };
+
+        let corners = session.map(|session| session.corners.as_slice()).unwrap_or_default();
+        let corner_distance = |number: u32| corners.iter()
+            .find(|corner| corner.number == number)
+            .map(|corner| corner.relative_distance)
+            .ok_or_else(|| format!("Slow motion range refers to turn {}, which is not in the session's corner data", number));
+
+        Ok(Range::Relative((corner_distance(first)? - CORNER_MARGIN).max(0.0), (corner_distance(last)? + CORNER_MARGIN).min(1.0)))
+    }
+}
+
+// A slow motion range as relative distance or in metres.
+enum Range {
+    Relative(f64, f64),
+    Metres(f64, f64)
+}
+
+impl Range {
+    fn contains(&self, t: &DriverTelemetryData) -> bool {
+        match *self {
+            Range::Relative(start, end) => (start..=end).contains(&t.relative_distance),
+            Range::Metres(start, end) => t.distance.is_some_and(|distance| (start..=end).contains(&distance))
+        }
+    }
+}
+
+/// Returns the shown samples of the lap as (lap frame, duration in ms) pairs.
+///
+/// Every sample lasts `1000 / data_framerate` ms scaled by the playback
+/// speed and the slowest slow motion range either driver is in. When samples
+/// are shorter than a frame at `output_framerate`, only the first sample at
+/// or after each frame's time is shown.
+pub fn lap_timeline(d1: &CompleteDriverData, d2: &CompleteDriverData, session: Option<&SessionData>,
+    data_framerate: u32, output_framerate: u32, playback_speed: f32, 
+    slow_motion: &[SlowMotion]) -> Result<Vec<(usize, f64)>, String> {
+    let ranges = slow_motion.iter()
+        .map(|range| Ok((range.resolve(session)?, range.speed as f64)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    if ranges.iter().any(|(range, _)| matches!(range, Range::Metres(..))) && !(has_distances(d1) && has_distances(d2)) {
+        return Err(String::from("Slow motion range by distance requires distance telemetry, fetch the laps again"));
+    }
+
+    let lap_length = d1.telemetry.len().max(d2.telemetry.len());
+    let frame_ms = 1000.0 / output_framerate as f64;
+
+    let mut timeline: Vec<(usize, f64)> = Vec::new();
+    let mut time = 0.0;
+    let mut frame_start = 0.0;
+    let mut next_frame_time = 0.0;
+
+    for i in 0..lap_length {
+        let samples = [d1.telemetry.get(i), d2.telemetry.get(i)].into_iter().flatten();
+        let slow_motion_speed = samples.flat_map(|t| ranges.iter()
+                .filter(move |(range, _)| range.contains(t))
+                .map(|(_, speed)| *speed))
+            .fold(1.0, f64::min);
+
+        // Frames are due on a fixed grid, so that the framerate is kept on
+        // average. The tolerance is for the floating point error accumulated in time.
+        if time >= next_frame_time - 1e-6 {
+            if let Some((_, duration)) = timeline.last_mut() {
+                *duration = time - frame_start;
+            }
+            timeline.push((i, 0.0));
+            frame_start = time;
+            while next_frame_time <= time + 1e-6 {
+                next_frame_time += frame_ms;
+            }
+        }
+
+        time += 1000.0 / (data_framerate as f64 * playback_speed as f64 * slow_motion_speed);
+    }
+
+    if let Some((_, duration)) = timeline.last_mut() {
+        *duration = time - frame_start;
+    }
+
+    Ok(timeline)
+}
+
+/// Converts frame durations in ms to GIF delays, which are whole multiples
+/// of 10 ms, carrying the rounding error over so the total stays accurate.
+pub fn frame_delays(durations: &[f64]) -> Vec<u32> {
+    let mut elapsed = 0.0;
+    let mut emitted = 0;
+
+    durations.iter().map(|duration| {
+        elapsed += duration;
+        let end = ((elapsed / 10.0).round() as u32).max(emitted + 1);
+        let delay = (end - emitted) * 10;
+        emitted = end;
+        delay
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_generator::test_utils::lap;
+
+    fn slow_motion(start: f64, end: f64, speed: f32) -> SlowMotion {
+        SlowMotion { start: Some(start), end: Some(end), corners: None, distance: None, speed }
+    }
+
+    fn timeline(d: &CompleteDriverData, data_framerate: u32, output_framerate: u32, playback_speed: f32,
+        slow_motion: &[SlowMotion]) -> Vec<(usize, f64)> {
+        lap_timeline(d, d, None, data_framerate, output_framerate, playback_speed, slow_motion).unwrap()
+    }
+
+    fn assert_durations(timeline: &[(usize, f64)], expected: &[f64]) {
+        assert_eq!(timeline.len(), expected.len());
+        for ((_, duration), expected) in timeline.iter().zip(expected) {
+            assert!((duration - expected).abs() < 1e-6, "{} != {}", duration, expected);
+        }
+    }
+
+    #[test]
+    fn plays_every_sample_in_real_time() {
+        let timeline = timeline(&lap(&[200; 21], 50), 20, 20, 1.0, &[]);
+
+        assert_eq!(timeline.iter().map(|(frame, _)| *frame).collect::<Vec<_>>(), (0..21).collect::<Vec<_>>());
+        assert_durations(&timeline, &[50.0; 21]);
+    }
+
+    #[test]
+    fn plays_at_half_speed() {
+        let timeline = timeline(&lap(&[200; 21], 50), 20, 20, 0.5, &[]);
+
+        assert_eq!(timeline.len(), 21);
+        assert_durations(&timeline, &[100.0; 21]);
+    }
+
+    #[test]
+    fn plays_at_double_speed() {
+        let timeline = timeline(&lap(&[200; 21], 50), 20, 20, 2.0, &[]);
+
+        assert_eq!(timeline.iter().map(|(frame, _)| *frame).collect::<Vec<_>>(), (0..21).step_by(2).collect::<Vec<_>>());
+        assert_durations(&timeline, &[[50.0; 10].as_slice(), &[25.0]].concat());
+    }
+
+    #[test]
+    fn keeps_a_lower_output_framerate_on_average() {
+        let timeline = timeline(&lap(&[200; 50], 20), 50, 20, 1.0, &[]);
+
+        assert_eq!(timeline.iter().map(|(frame, _)| *frame).take(5).collect::<Vec<_>>(), vec![0, 3, 5, 8, 10]);
+        assert_durations(&timeline[..4], &[60.0, 40.0, 60.0, 40.0]);
+        assert_eq!(timeline.len(), 20);
+        assert!((timeline.iter().map(|(_, duration)| duration).sum::<f64>() - 1000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn plays_the_slowest_slow_motion_range() {
+        let ranges = [slow_motion(0.5, 1.0, 0.5), slow_motion(0.75, 1.0, 0.25)];
+        let timeline = timeline(&lap(&[200; 5], 50), 20, 20, 1.0, &ranges);
+
+        assert_durations(&timeline, &[50.0, 50.0, 100.0, 200.0, 200.0]);
+    }
+
+    #[test]
+    fn slows_down_while_either_driver_is_in_a_range() {
+        let (d1, mut d2) = (lap(&[200; 5], 50), lap(&[200; 5], 50));
+        d2.telemetry[1].relative_distance = 0.6;
+
+        let timeline = lap_timeline(&d1, &d2, None, 20, 20, 1.0, &[slow_motion(0.5, 0.7, 0.5)]).unwrap();
+
+        assert_durations(&timeline, &[50.0, 100.0, 100.0, 50.0, 50.0]);
+    }
+
+    #[test]
+    fn rejects_unresolvable_ranges() {
+        let d = lap(&[200; 5], 50);
+        let corners = SlowMotion { start: None, end: None, corners: Some((1, 2)), distance: None, speed: 0.5 };
+        assert!(lap_timeline(&d, &d, None, 20, 20, 1.0, &[corners]).unwrap_err().contains("turn 1"));
+
+        let mut without_distances = lap(&[200; 5], 50);
+        without_distances.telemetry[2].distance = None;
+        let metres = SlowMotion { start: None, end: None, corners: None, distance: Some((0.0, 10.0)), speed: 0.5 };
+        assert!(lap_timeline(&d, &without_distances, None, 20, 20, 1.0, &[metres]).is_err());
+    }
+
+    #[test]
+    fn validates_ranges() {
+        assert!(slow_motion(0.2, 0.4, 0.5).validate().is_ok());
+        assert!(slow_motion(0.4, 0.2, 0.5).validate().is_err());
+        assert!(slow_motion(0.2, 1.5, 0.5).validate().is_err());
+        assert!(slow_motion(0.2, 0.4, 0.0).validate().is_err());
+        assert!(SlowMotion { corners: Some((3, 5)), ..slow_motion(0.2, 0.4, 0.5) }.validate().is_err());
+    }
+
+    #[test]
+    fn delays_sum_to_the_total_duration() {
+        let durations = [1000.0 / 15.0; 45];
+        let delays = frame_delays(&durations);
+
+        assert!(delays.iter().all(|delay| delay % 10 == 0 && *delay > 0));
+        assert_eq!(delays.iter().sum::<u32>(), 3000);
+        assert_eq!(&delays[..3], &[70, 60, 70]);
+    }
+
+    #[test]
+    fn delays_are_at_least_one_centisecond() {
+        assert_eq!(frame_delays(&[4.0, 4.0, 30.0]), vec![10, 10, 20]);
+    }
+}
@@ -0,0 +1,445 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use super::{FollowCameraConfig, FontConfig, LapPortion, SlowMotion, Theme, TraceColoring, Units, WatermarkConfig, MAX_OUTPUT_FRAMERATE};
+
+// Size of the default layout, which is used as the reference for scaling.
+const BASE_SIZE: u32 = 768;
+
+/// Named output resolutions accepted by `RenderConfig::parse_resolution`.
+pub const RESOLUTION_PRESETS: [(&str, (u32, u32)); 4] = [
+    ("square", (768, 768)),
+    ("feed", (1080, 1080)),
+    ("landscape", (1280, 720)),
+    ("story", (720, 1280))
+];
+
+/// Arrangement of the panels.
+///
+/// In the portrait layout the HUD is a sidebar to the right of the track map
+/// and the telemetry panel spans the full width below both. In the landscape
+/// layout the track map takes the full height on the left, with the drivers'
+/// stats side by side in two `sidebar_width` columns above the telemetry panel.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Portrait,
+    Landscape
+}
+
+/// Dimensions, fonts, margins and the theme of the rendered animation.
+///
+/// Defaults reproduce the original 768×768 layout. A config can be loaded
+/// from a JSON file, in which any omitted field keeps its default value.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderConfig {
+    pub orientation: Orientation,
+
+    pub padding: u32,
+    pub padding_lr: u32,
+    pub padding_tb: u32,
+    pub padding_tb_inner: u32,
+
+    pub track_width: u32,
+    pub track_height: u32,
+    pub sidebar_width: u32,
+    pub telemetry_height: u32,
+    pub driver_stats_height: u32,
+    /// Whether the built-in layouts show a header with the session's event
+    /// across the top, if session data is available.
+    pub header: bool,
+    pub header_height: u32,
+
+    pub telemetry_plot_axes_labels_margin: u32,
+    pub telemetry_label_margin: u32,
+    pub driver_team_margin: i32,
+    pub name_lap_speed_margin: u32,
+    pub sector_times_margin: u32,
+
+    pub telemetry_label_font_size: u32,
+    pub driver_font_size: u32,
+    pub team_font_size: u32,
+    pub lap_speed_font_size: u32,
+    pub sector_font_size: u32,
+    pub fonts: FontConfig,
+    /// Whether the HUD shows each driver's tyre compound and tyre age.
+    pub tyre_info: bool,
+
+    pub theme: Theme,
+    /// Units of speeds and distances.
+    pub units: Units,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<WatermarkConfig>,
+
+    pub thickness: i32,
+    /// What the traces on the track map are colored by.
+    pub trace_coloring: TraceColoring,
+    /// RGB colors of the drivers used instead of their team colors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver1_color: Option<[u8; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver2_color: Option<[u8; 3]>,
+    /// Whether the second driver's trace is dashed if both drivers are team-mates.
+    pub dash_teammates: bool,
+    pub follow_camera: FollowCameraConfig,
+    /// Whether the speed plot marks the session's corners.
+    pub corner_markers: bool,
+    /// Whether the speed plot labels each driver's apexes with their speed.
+    pub apex_speeds: bool,
+    /// Whether the speed plot shows each driver's current distance and speed.
+    pub progress_cursor: bool,
+
+    /// Seconds the title card is shown for before the lap, 0 to leave it out.
+    pub title_card_seconds: f32,
+    /// Seconds the first frame of the lap is held for.
+    pub intro_seconds: f32,
+    /// Seconds the last frame of the lap is held for after the slower driver finishes.
+    pub outro_seconds: f32,
+    /// Seconds the summary card is shown for after the lap, 0 to leave it out.
+    pub summary_card_seconds: f32,
+
+    /// Frames per second of the GIF, up to 50. Defaults to the framerate the
+    /// telemetry was fetched with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_framerate: Option<u32>,
+    /// Speed of the lap relative to real time, e.g. 0.5 for half speed.
+    pub playback_speed: f32,
+    /// Parts of the lap played at a different speed.
+    pub slow_motion: Vec<SlowMotion>,
+
+    /// Part of the lap to render instead of the whole lap.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lap_portion: Option<LapPortion>
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            orientation: Orientation::Portrait,
+
+            padding: 15,
+            padding_lr: 20,
+            padding_tb: 38,
+            padding_tb_inner: 10,
+
+            track_width: 512,
+            track_height: 512,
+            sidebar_width: 256,
+            telemetry_height: 256,
+            driver_stats_height: 200,
+            header: true,
+            header_height: 64,
+
+            telemetry_plot_axes_labels_margin: 32,
+            telemetry_label_margin: 5,
+            driver_team_margin: -5,
+            name_lap_speed_margin: 5,
+            sector_times_margin: 3,
+
+            telemetry_label_font_size: 9,
+            driver_font_size: 20,
+            team_font_size: 12,
+            lap_speed_font_size: 24,
+            sector_font_size: 12,
+            fonts: FontConfig::default(),
+            tyre_info: true,
+
+            theme: Theme::default(),
+            units: Units::Metric,
+            watermark: None,
+
+            thickness: 3,
+            trace_coloring: TraceColoring::Team,
+            driver1_color: None,
+            driver2_color: None,
+            dash_teammates: true,
+            follow_camera: FollowCameraConfig::default(),
+            corner_markers: true,
+            apex_speeds: true,
+            progress_cursor: true,
+
+            title_card_seconds: 0.0,
+            intro_seconds: 0.0,
+            outro_seconds: 1.0,
+            summary_card_seconds: 0.0,
+
+            output_framerate: None,
+            playback_speed: 1.0,
+            slow_motion: Vec::new(),
+
+            lap_portion: None
+        }
+    }
+}
+
+impl RenderConfig {
+    /// Loads a config from a JSON file.
+    pub fn from_file(path: &str) -> Result<RenderConfig, String> {
+        RenderConfig::default().with_overrides_from_file(path)
+    }
+
+    /// Loads a JSON file whose fields override the ones of this config.
+    pub fn with_overrides_from_file(&self, path: &str) -> Result<RenderConfig, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read config file {}: {}", path, e))?;
+        let overrides: serde_json::Value = serde_json::from_str(&json)
+            .map_err(|e| format!("Unable to parse config file {}: {}", path, e))?;
+        let overrides = overrides.as_object()
+            .ok_or_else(|| format!("Config file {} must contain a JSON object", path))?;
+
+        let mut merged = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        if let Some(fields) = merged.as_object_mut() {
+            fields.extend(overrides.clone());
+        }
+
+        let config: RenderConfig = serde_json::from_value(merged)
+            .map_err(|e| format!("Unable to parse config file {}: {}", path, e))?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses a preset name (see `RESOLUTION_PRESETS`) or a custom `<width>x<height>`.
+    pub fn parse_resolution(resolution: &str) -> Result<(u32, u32), String> {
+        if let Some((_, size)) = RESOLUTION_PRESETS.iter().find(|(name, _)| *name == resolution) {
+            return Ok(*size);
+        }
+
+        let invalid = || format!("Invalid resolution {}, expected one of: {} or <width>x<height>", resolution,
+            RESOLUTION_PRESETS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", "));
+
+        let (width, height) = resolution.split_once('x').ok_or_else(invalid)?;
+        let width = width.parse::<u32>().map_err(|_| invalid())?;
+        let height = height.parse::<u32>().map_err(|_| invalid())?;
+
+        if width < 256 || height < 256 || width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(String::from("Resolution must be between 256 and 65535 pixels in each dimension"));
+        }
+
+        Ok((width, height))
+    }
+
+    /// Returns the default config scaled proportionally to the given resolution.
+    /// Resolutions clearly wider than tall use the landscape layout. Sizes too
+    /// small for the panels give a config which `validate` rejects.
+    pub fn for_resolution(width: u32, height: u32) -> RenderConfig {
+        let base = RenderConfig::default();
+        let ratio = width.min(height) as f32 / BASE_SIZE as f32;
+        let scale = |value: u32| ((value as f32 * ratio).round() as u32).max(1);
+
+        let mut config = RenderConfig {
+            padding: scale(base.padding),
+            padding_lr: scale(base.padding_lr),
+            padding_tb: scale(base.padding_tb),
+            padding_tb_inner: scale(base.padding_tb_inner),
+
+            driver_stats_height: scale(base.driver_stats_height),
+            header_height: scale(base.header_height),
+
+            telemetry_plot_axes_labels_margin: scale(base.telemetry_plot_axes_labels_margin),
+            telemetry_label_margin: scale(base.telemetry_label_margin),
+            driver_team_margin: (base.driver_team_margin as f32 * ratio).round() as i32,
+            name_lap_speed_margin: scale(base.name_lap_speed_margin),
+            sector_times_margin: scale(base.sector_times_margin),
+
+            telemetry_label_font_size: scale(base.telemetry_label_font_size),
+            driver_font_size: scale(base.driver_font_size),
+            team_font_size: scale(base.team_font_size),
+            lap_speed_font_size: scale(base.lap_speed_font_size),
+            sector_font_size: scale(base.sector_font_size),
+
+            thickness: scale(base.thickness as u32) as i32,
+
+            ..base.clone()
+        };
+
+        let sidebar_width = scale(base.sidebar_width);
+        let telemetry_height = scale(base.telemetry_height);
+
+        if width as f32 > 1.2 * height as f32 {
+            config.orientation = Orientation::Landscape;
+            config.track_height = height;
+            config.track_width = height.min(width.saturating_sub(2 * sidebar_width));
+            config.sidebar_width = (width - config.track_width) / 2;
+            config.track_width = width - 2 * config.sidebar_width;
+            config.telemetry_height = height.saturating_sub(config.hud_stats_height());
+        } else {
+            config.orientation = Orientation::Portrait;
+            config.sidebar_width = sidebar_width;
+            config.track_width = width.saturating_sub(sidebar_width);
+            config.track_height = config.track_width.max(height.saturating_sub(2 * telemetry_height))
+                .min(height.saturating_sub(telemetry_height));
+            config.telemetry_height = height - config.track_height;
+        }
+
+        config
+    }
+
+    /// Checks that all panels fit into the layout.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.track_width <= 2 * self.padding || self.track_height <= 2 * self.padding {
+            return Err(String::from("Track area must be larger than twice the padding"));
+        }
+
+        if self.telemetry_height <= 2 * self.padding + self.telemetry_plot_axes_labels_margin
+            || self.telemetry_panel_width() <= 2 * self.padding + self.telemetry_plot_axes_labels_margin {
+            return Err(String::from("Telemetry panel is too small for its padding and axes labels"));
+        }
+
+        match self.orientation {
+            Orientation::Portrait if self.track_height < 2 * (self.padding_tb + self.driver_stats_height) => 
+                return Err(String::from("Track area is too short to fit both drivers' stats")),
+            Orientation::Landscape if self.track_height < self.hud_stats_height() + self.telemetry_height => 
+                return Err(String::from("Track area is too short to fit the drivers' stats and the telemetry panel")),
+            _ => ()
+        }
+
+        if self.gif_width() > u16::MAX as u32 || self.gif_height() > u16::MAX as u32 {
+            return Err(String::from("GIF dimensions must not exceed 65535 pixels"));
+        }
+
+        if self.thickness <= 0 {
+            return Err(String::from("Line thickness must be positive"));
+        }
+
+        let durations = [self.title_card_seconds, self.intro_seconds, self.outro_seconds, self.summary_card_seconds];
+        if durations.iter().any(|seconds| !seconds.is_finite() || *seconds < 0.0) {
+            return Err(String::from("Card, intro and outro durations must not be negative"));
+        }
+
+        if let Some(watermark) = &self.watermark {
+            watermark.validate()?;
+        }
+
+        self.follow_camera.validate()?;
+
+        if self.output_framerate.is_some_and(|framerate| !(1..=MAX_OUTPUT_FRAMERATE).contains(&framerate)) {
+            return Err(format!("Output framerate must be between 1 and {}", MAX_OUTPUT_FRAMERATE));
+        }
+
+        if !self.playback_speed.is_finite() || self.playback_speed <= 0.0 {
+            return Err(String::from("Playback speed must be positive"));
+        }
+
+        for range in &self.slow_motion {
+            range.validate()?;
+        }
+
+        if let Some(lap_portion) = &self.lap_portion {
+            lap_portion.validate()?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the panels of the built-in layouts still fit below the
+    /// header, for when they show it.
+    pub fn validate_header(&self) -> Result<(), String> {
+        if self.track_height <= self.header_height + 2 * self.padding {
+            return Err(String::from("Track area is too short to fit the header"));
+        }
+
+        let stats_top = self.driver_stats_top(true);
+        match self.orientation {
+            Orientation::Portrait if stats_top + 2 * self.driver_stats_height + self.padding_tb > self.track_height =>
+                Err(String::from("Track area is too short to fit the header and both drivers' stats")),
+            Orientation::Landscape if self.telemetry_height 
+                <= stats_top - self.padding_tb + 2 * self.padding + self.telemetry_plot_axes_labels_margin =>
+                Err(String::from("Telemetry panel is too small for the header, its padding and axes labels")),
+            _ => Ok(())
+        }
+    }
+
+    /// Top of the first driver's stats in the built-in layouts, below the
+    /// header if it is shown.
+    pub fn driver_stats_top(&self, header: bool) -> u32 {
+        if header { self.padding_tb.max(self.header_height) } else { self.padding_tb }
+    }
+
+    pub fn gif_width(&self) -> u32 {
+        match self.orientation {
+            Orientation::Portrait => self.track_width + self.sidebar_width,
+            Orientation::Landscape => self.track_width + 2 * self.sidebar_width
+        }
+    }
+
+    pub fn gif_height(&self) -> u32 {
+        match self.orientation {
+            Orientation::Portrait => self.track_height + self.telemetry_height,
+            Orientation::Landscape => self.track_height
+        }
+    }
+
+    // Height of a single driver's stats block including its outer padding.
+    pub fn hud_stats_height(&self) -> u32 {
+        self.driver_stats_height + 2 * self.padding_tb
+    }
+
+    pub fn telemetry_panel_width(&self) -> u32 {
+        match self.orientation {
+            Orientation::Portrait => self.gif_width(),
+            Orientation::Landscape => 2 * self.sidebar_width
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_presets_and_custom_resolutions() {
+        assert_eq!(RenderConfig::parse_resolution("landscape"), Ok((1280, 720)));
+        assert_eq!(RenderConfig::parse_resolution("1000x800"), Ok((1000, 800)));
+
+        for invalid in ["", "huge", "1000", "1000x", "x800", "-1000x800", "1000x800x2"] {
+            assert!(RenderConfig::parse_resolution(invalid).unwrap_err().starts_with("Invalid resolution"), "{}", invalid);
+        }
+
+        for out_of_range in ["255x800", "800x100", "70000x800"] {
+            assert!(RenderConfig::parse_resolution(out_of_range).unwrap_err().starts_with("Resolution must be"));
+        }
+    }
+
+    #[test]
+    fn base_resolution_is_the_default() {
+        assert_eq!(RenderConfig::for_resolution(BASE_SIZE, BASE_SIZE), RenderConfig::default());
+    }
+
+    #[test]
+    fn scaled_configs_fill_the_resolution() {
+        let sizes = RESOLUTION_PRESETS.iter().map(|(_, size)| *size)
+            .chain([(256, 256), (300, 1000), (1000, 300), (800, 900), (4000, 2000)]);
+
+        for (width, height) in sizes {
+            let config = RenderConfig::for_resolution(width, height);
+
+            assert_eq!((config.gif_width(), config.gif_height()), (width, height));
+            assert!(config.validate().is_ok(), "{}x{}: {:?}", width, height, config.validate());
+            assert!(config.validate_header().is_ok(), "{}x{}: {:?}", width, height, config.validate_header());
+        }
+    }
+
+    #[test]
+    fn wide_resolutions_are_landscape() {
+        assert_eq!(RenderConfig::for_resolution(1280, 720).orientation, Orientation::Landscape);
+        assert_eq!(RenderConfig::for_resolution(720, 1280).orientation, Orientation::Portrait);
+        assert_eq!(RenderConfig::for_resolution(1080, 1080).orientation, Orientation::Portrait);
+    }
+
+    #[test]
+    fn degenerate_resolutions_do_not_panic() {
+        for (width, height) in [(40, 30), (30, 40), (2, 2), (4000, 2), (2, 4000)] {
+            let config = RenderConfig::for_resolution(width, height);
+
+            assert!(config.validate().is_err() || (config.gif_width(), config.gif_height()) == (width, height),
+                "{}x{}", width, height);
+        }
+
+        for (width, height) in [(1, 1), (4000, 1), (1, 4000)] {
+            assert!(RenderConfig::for_resolution(width, height).validate().is_err(), "{}x{}", width, height);
+        }
+    }
+}
@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+
+use image::Rgba;
+
+use crate::data_fetcher::{CompleteDriverData, DriverTelemetryData, SessionData};
+
+use super::{apex_indices, apex_label_position, badge_letter_size, corner_labels_start, corner_marker_position, corner_ticks, distance_axis, 
+    driver2_dashes, lap_compound, plot_base_size, speed_axis, trace_width, tyre_age_str, x_tick_position, y_tick_position, 
+    Axis, Corner, DeltaPlot, DistanceRange, FollowCamera, Fonts, FontStyle, Header, Layout, PanelKind, PanelSpec, Rect, RenderConfig, TelemetryPlot, 
+    TextCache, TraceColoring, TraceColors, GRIDLINE_ALPHA, HUD, LEGEND_BAR_WIDTH, RAMP_STOPS, TICK_LENGTH};
+
+// Renders the final state of the comparison (the full traces and the final
+// HUD) as an SVG document. The geometry follows the raster panels, but the
+// traces, axes and text are kept as vector elements.
+pub struct SvgRenderer<'a> {
+    d1: &'a CompleteDriverData,
+    d2: &'a CompleteDriverData,
+    d1_color: Rgba<u8>,
+    d2_color: Rgba<u8>,
+
+    hud: HUD<'a>,
+    text_cache: &'a TextCache<'a>,
+    config: &'a RenderConfig,
+    // The font-family and font-weight attributes of every style.
+    font_attributes: HashMap<FontStyle, String>,
+
+    svg: String
+}
+
+impl <'a> SvgRenderer<'a> {
+    pub fn new(d1: &'a CompleteDriverData, d2: &'a CompleteDriverData, (d1_color, d2_color): (Rgba<u8>, Rgba<u8>),
+        fonts: &Fonts, text_cache: &'a TextCache<'a>, config: &'a RenderConfig) -> SvgRenderer<'a> {
+        let font_attributes = [FontStyle::Regular, FontStyle::Bold, FontStyle::Timing].into_iter()
+            .map(|style| (style, Self::font_attributes(fonts, style))).collect();
+
+        SvgRenderer { d1, d2, d1_color, d2_color,
+            hud: HUD::new(d1, d2, text_cache, config), text_cache, config, font_attributes, svg: String::new() }
+    }
+
+    pub fn render(mut self, layout: &Layout, panels: &[(PanelSpec, Rect)], session: Option<&SessionData>) -> Result<String, String> {
+        self.svg.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+            layout.width, layout.height));
+        self.svg.push_str(&format!("<rect width=\"{}\" height=\"{}\" {}/>\n",
+            layout.width, layout.height, Self::paint("fill", self.config.theme.background())));
+
+        for (spec, rect) in panels {
+            match spec.kind {
+                PanelKind::TrackMap => self.track_map(*rect),
+                PanelKind::FollowCamera => self.follow_camera(*rect),
+                PanelKind::SpeedPlot => self.speed_plot(*rect, session),
+                PanelKind::DeltaPlot => self.delta_plot(*rect),
+                PanelKind::DriverStats => self.driver_stats(spec.driver.unwrap_or(1), *rect),
+                PanelKind::Title => {
+                    let text = spec.text.clone().unwrap_or_else(||
+                        format!("{} vs {}", self.d1.driver.broadcast_name, self.d2.driver.broadcast_name));
+                    self.title(&text, *rect);
+                },
+                PanelKind::Header => self.header(session, spec.text.as_deref(), *rect)
+            }
+        }
+
+        self.watermark(layout)?;
+
+        self.svg.push_str("</svg>\n");
+        Ok(self.svg)
+    }
+
+    // Color attributes, e.g. `fill="#rrggbb" fill-opacity="0.5"`.
+    fn paint(attribute: &str, color: Rgba<u8>) -> String {
+        let paint = format!("{}=\"#{:02x}{:02x}{:02x}\"", attribute, color[0], color[1], color[2]);
+
+        match color[3] {
+            255 => paint,
+            alpha => format!("{} {}-opacity=\"{:.3}\"", paint, attribute, alpha as f32 / 255.0)
+        }
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+    }
+
+    // Names the families of the style's fallback chain, so that viewers with
+    // the fonts installed draw the text with the glyphs it was measured with.
+    fn font_attributes(fonts: &Fonts, style: FontStyle) -> String {
+        let generic = if fonts.is_monospaced(style) { "monospace" } else { "sans-serif" };
+        let families = fonts.families(style).iter()
+            .map(|family| format!("'{}'", family.replace(['\'', '\\'], "")))
+            .chain([String::from(generic)])
+            .collect::<Vec<_>>().join(", ");
+
+        let attributes = format!("font-family=\"{}\"", Self::escape(&families));
+        match fonts.weight(style) {
+            400 => attributes,
+            weight => format!("{} font-weight=\"{}\"", attributes, weight)
+        }
+    }
+
+    fn polyline(&mut self, points: &[(f64, f64)], color: Rgba<u8>, width: f64) {
+        self.dashed_polyline(points, color, width, None, 0.0);
+    }
+
+    // Draws a polyline with the `(dash, gap)` pattern, if any, starting at `phase`.
+    fn dashed_polyline(&mut self, points: &[(f64, f64)], color: Rgba<u8>, width: f64, dashes: Option<(f64, f64)>, 
+        phase: f64) {
+        let points = points.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect::<Vec<_>>().join(" ");
+        let dashes = match dashes {
+            Some((dash, gap)) => format!(" stroke-dasharray=\"{:.2} {:.2}\" stroke-dashoffset=\"{:.2}\"", dash, gap, phase),
+            None => String::new()
+        };
+
+        self.svg.push_str(&format!(
+            "<polyline points=\"{}\" fill=\"none\" {} stroke-width=\"{}\"{} stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n",
+            points, Self::paint("stroke", color), width, dashes));
+    }
+
+    fn line(&mut self, (x1, y1): (f64, f64), (x2, y2): (f64, f64), color: Rgba<u8>) {
+        self.svg.push_str(&format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {} stroke-width=\"1\"/>\n",
+            x1, y1, x2, y2, Self::paint("stroke", color)));
+    }
+
+    // Draws the text with the top of its line at `top`, like the raster text.
+    // `anchor` is the SVG text-anchor: start, middle or end.
+    fn text(&mut self, (x, top): (f64, f64), style: FontStyle, size: u32, color: Rgba<u8>, anchor: &str, text: &str) {
+        // Font sizes are in points, like in ab_glyph's pt_to_px_scale.
+        let font_size = size as f64 * 96.0 / 72.0;
+        let baseline = top + self.text_cache.ascent(style, size) as f64;
+
+        self.svg.push_str(&format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" {} font-size=\"{:.2}\" {} text-anchor=\"{}\">{}</text>\n",
+            x, baseline, self.font_attributes[&style], font_size, Self::paint("fill", color), anchor, Self::escape(text)));
+    }
+
+    // Draws both drivers' traces offset by (x, y), joining consecutive
+    // segments of the same color into one line.
+    fn traces(&mut self, (x, y): (f64, f64), width: f64) {
+        let colors = TraceColors::new(self.d1, self.d2, self.config);
+        let d2_dashes = driver2_dashes(&self.d1.driver, &self.d2.driver, self.config, width as f32)
+            .map(|(dash, gap)| (dash as f64, gap as f64));
+
+        for (driver, driver_color, dashes) in [(self.d1, self.d1_color, None), (self.d2, self.d2_color, d2_dashes)] {
+            let points: Vec<(f64, f64)> = driver.telemetry.iter()
+                .map(|t| (x + t.y as f64, y + t.x as f64)).collect();
+            let segment_colors: Vec<Rgba<u8>> = (0..points.len().saturating_sub(1))
+                .map(|i| colors.segment_color(driver, driver_color, i)).collect();
+
+            let (mut start, mut phase, mut length) = (0, 0.0, 0.0);
+            for end in 1..points.len() {
+                length += (points[end].0 - points[end - 1].0).hypot(points[end].1 - points[end - 1].1);
+
+                if end + 1 == points.len() || segment_colors[end] != segment_colors[end - 1] {
+                    self.dashed_polyline(&points[start..=end], segment_colors[end - 1], width, dashes, phase);
+                    (start, phase) = (end, length);
+                }
+            }
+        }
+    }
+
+    fn track_map(&mut self, rect: Rect) {
+        self.traces((rect.x as f64, rect.y as f64), trace_width(self.config.thickness) as f64);
+        self.trace_legend(rect);
+    }
+
+    // Like the raster legend, in the bottom left corner of the track map.
+    fn trace_legend(&mut self, rect: Rect) {
+        let colors = TraceColors::new(self.d1, self.d2, self.config);
+        let size = self.config.telemetry_label_font_size;
+        let foreground = self.config.theme.foreground();
+        let height = self.text_cache.line_height(FontStyle::Regular, size) as f64;
+        let swatch_size = (height / 2.0).floor().max(1.0);
+        let gap = self.config.telemetry_label_margin as f64;
+        let text_cache = self.text_cache;
+        let label_width = |text: &str| text_cache.get_text(FontStyle::Regular, size, foreground, text).width() as f64;
+
+        let mut x = (rect.x + self.config.padding) as f64;
+        let y = (rect.y + rect.height - self.config.padding) as f64 - height;
+        let swatch_y = y + ((height - swatch_size) / 2.0).floor();
+        let entries = colors.legend_entries();
+
+        match colors.coloring() {
+            TraceColoring::Team => (),
+            TraceColoring::Speed => {
+                let stops = (0..RAMP_STOPS).map(|i| {
+                    let ratio = i as f32 / (RAMP_STOPS - 1) as f32;
+                    let color = colors.ramp_color(ratio);
+                    format!("<stop offset=\"{}\" stop-color=\"#{:02x}{:02x}{:02x}\"/>", ratio, color[0], color[1], color[2])
+                }).collect::<String>();
+                self.svg.push_str(&format!("<defs><linearGradient id=\"speed-ramp\">{}</linearGradient></defs>\n", stops));
+
+                self.text((x, y), FontStyle::Regular, size, foreground, "start", &entries[0].1);
+                x += label_width(&entries[0].1) + gap;
+                self.svg.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"url(#speed-ramp)\"/>\n",
+                    x, swatch_y, LEGEND_BAR_WIDTH, swatch_size));
+                x += LEGEND_BAR_WIDTH as f64 + gap;
+                self.text((x, y), FontStyle::Regular, size, foreground, "start", &entries[1].1);
+            },
+            TraceColoring::Pedals => for (color, text) in &entries {
+                self.svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" {3}/>\n",
+                    x, swatch_y, swatch_size, Self::paint("fill", Rgba([color[0], color[1], color[2], 255]))));
+                x += swatch_size + gap;
+                self.text((x, y), FontStyle::Regular, size, foreground, "start", text);
+                x += label_width(text) + gap;
+            }
+        }
+    }
+
+    // The view of the last frame, as a nested viewport over the unzoomed
+    // traces, so that only the stroke width has to be scaled back.
+    fn follow_camera(&mut self, rect: Rect) {
+        let zoom = self.config.follow_camera.zoom as f64;
+        let (center_x, center_y) = FollowCamera::final_center(self.d1, self.d2, &self.config.follow_camera);
+        let (width, height) = (rect.width as f64 / zoom, rect.height as f64 / zoom);
+
+        self.svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>\n",
+            rect.x, rect.y, rect.width, rect.height, Self::paint("fill", self.config.theme.background())));
+        self.svg.push_str(&format!(
+            "<svg x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\">\n",
+            rect.x, rect.y, rect.width, rect.height,
+            center_x / zoom - width / 2.0, center_y / zoom - height / 2.0, width, height));
+
+        self.traces((0.0, 0.0), trace_width(self.config.thickness) as f64 / zoom);
+
+        self.svg.push_str("</svg>\n");
+        self.svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" {} stroke-width=\"1\"/>\n",
+            rect.x as f64 + 0.5, rect.y as f64 + 0.5, rect.width - 1, rect.height - 1,
+            Self::paint("stroke", self.config.theme.grid())));
+    }
+
+    // Draws the axes with their captions and ticks, returning the position and size of the plot area.
+    fn plot_base(&mut self, rect: Rect, x_axis: &Axis, y_axis: &Axis) -> (f64, f64, f64, f64) {
+        let (base_width, base_height) = plot_base_size(self.config, rect.width, rect.height);
+        let (width, height) = (base_width as f64, base_height as f64);
+        let x = (rect.x + self.config.padding) as f64;
+        let y = (rect.y + self.config.padding) as f64;
+        let margin = self.config.telemetry_plot_axes_labels_margin as f64;
+        let label_margin = self.config.telemetry_label_margin as f64;
+        let label_size = self.config.telemetry_label_font_size;
+        let foreground = self.config.theme.foreground();
+        let axis = self.config.theme.axis();
+
+        let label_height = self.text_cache.line_height(FontStyle::Regular, label_size) as f64;
+        self.text((x + (width - margin) / 2.0 + margin, y + height - label_height),
+            FontStyle::Regular, label_size, foreground, "middle", &x_axis.label);
+
+        self.svg.push_str(&format!("<g transform=\"translate({:.1},{:.1}) rotate(-90)\">\n",
+            x + margin - label_margin - label_height / 2.0, y + (height - margin) / 2.0));
+        self.text((0.0, -label_height / 2.0), FontStyle::Regular, label_size, foreground, "middle", &y_axis.label);
+        self.svg.push_str("</g>\n");
+
+        let Rgba([r, g, b, _]) = self.config.theme.grid();
+        for tick in &y_axis.ticks {
+            let label = self.text_cache.get_text(FontStyle::Regular, label_size, foreground, &tick.label);
+            let (tick_y, (label_x, label_y)) = y_tick_position(self.config, base_height, tick, label.height());
+            let tick_y = y + tick_y as f64;
+
+            self.line((x + margin - TICK_LENGTH as f64, tick_y), (x + margin, tick_y), axis);
+            if tick.position > 0.0 {
+                let gridline_start = x + (label_x as u32 + label.width()) as f64 + label_margin;
+                self.line((gridline_start, tick_y), (x + width, tick_y), Rgba([r, g, b, GRIDLINE_ALPHA]));
+            }
+            self.text((x + label_x as f64, y + label_y as f64), FontStyle::Regular, label_size, foreground, "start", &tick.label);
+        }
+
+        for tick in &x_axis.ticks {
+            let label = self.text_cache.get_text(FontStyle::Regular, label_size, foreground, &tick.label);
+            let (tick_x, (label_x, label_y)) = x_tick_position(self.config, (base_width, base_height), tick, label.width());
+            let tick_x = x + tick_x as f64;
+
+            self.line((tick_x, y + height - margin), (tick_x, y + height - margin + TICK_LENGTH as f64), axis);
+            self.text((x + label_x as f64, y + label_y as f64), FontStyle::Regular, label_size, foreground, "start", &tick.label);
+        }
+
+        self.line((x + margin, y + height - margin), (x + width, y + height - margin), axis);
+        self.line((x + margin, y), (x + margin, y + height - margin), axis);
+
+        (x + margin, y, width - margin, height - margin)
+    }
+
+    fn speed_plot(&mut self, rect: Rect, session: Option<&SessionData>) {
+        let (base_width, base_height) = plot_base_size(self.config, rect.width, rect.height);
+        let margin = self.config.telemetry_plot_axes_labels_margin;
+        let max_speed = TelemetryPlot::get_max_speed(self.d1, self.d2);
+        let (speed_scale, speed_axis) = speed_axis(self.text_cache, self.config, max_speed, base_height - margin);
+        let distance_range = DistanceRange::new(self.config, self.d1, self.d2);
+        let distance_axis = distance_axis(self.text_cache, self.config, distance_range, base_width - margin);
+
+        let (x, y, width, height) = self.plot_base(rect, &distance_axis, &speed_axis);
+        let label_size = self.config.telemetry_label_font_size;
+
+        if self.config.corner_markers {
+            let grid = self.config.theme.grid();
+            let label_margin = self.config.telemetry_label_margin as i64;
+            let mut previous_label_end = corner_labels_start(self.text_cache, self.config, &speed_axis);
+
+            for tick in corner_ticks(session, distance_range) {
+                let label = self.text_cache.get_text(FontStyle::Regular, label_size, grid, &tick.label);
+                let (marker_x, (label_x, label_y)) = corner_marker_position(self.config, base_width, &tick, label.width());
+                let base_x = x - margin as f64;
+
+                self.line((base_x + marker_x as f64, y + label.height() as f64), (base_x + marker_x as f64, y + height), 
+                    Rgba([grid[0], grid[1], grid[2], GRIDLINE_ALPHA]));
+                if label_x >= previous_label_end {
+                    self.text((base_x + label_x as f64, y + label_y as f64), FontStyle::Regular, label_size, grid, "start", &tick.label);
+                    previous_label_end = label_x + label.width() as i64 + label_margin;
+                }
+            }
+        }
+
+        let point = |t: &DriverTelemetryData|
+            (x + width * distance_range.position(t), y + height - height * t.speed as f64 / speed_scale);
+
+        for (slot, (driver, color)) in [(self.d1, self.d1_color), (self.d2, self.d2_color)].into_iter().enumerate() {
+            let points: Vec<(f64, f64)> = driver.telemetry.iter().map(point).collect();
+            self.polyline(&points, color, 1.0);
+
+            if !self.config.apex_speeds {
+                continue;
+            }
+
+            let label_color = Rgba([color[0], color[1], color[2], 255]);
+            for apex in apex_indices(&driver.telemetry) {
+                let speed = self.config.units.round_speed(driver.telemetry[apex].speed).to_string();
+                let label = self.text_cache.get_text(FontStyle::Regular, label_size, label_color, &speed);
+                let (apex_x, apex_y) = points[apex];
+                let (label_x, label_y) = apex_label_position(self.config, ((apex_x - x) as f32, (apex_y - y) as f32), 
+                    label.dimensions(), (width as u32, height as u32), slot as u32);
+
+                self.text((x + label_x as f64, y + label_y as f64), FontStyle::Regular, label_size, label_color, "start", &speed);
+            }
+        }
+    }
+
+    fn delta_plot(&mut self, rect: Rect) {
+        let (base_width, _) = plot_base_size(self.config, rect.width, rect.height);
+        let distance_range = DistanceRange::new(self.config, self.d1, self.d2);
+        let distance_axis = distance_axis(self.text_cache, self.config, distance_range,
+            base_width - self.config.telemetry_plot_axes_labels_margin);
+        let delta_axis = Axis { label: String::from("DELTA"), ticks: Vec::new() };
+
+        let (x, y, width, height) = self.plot_base(rect, &distance_axis, &delta_axis);
+
+        let zero_y = y + (height / 2.0).floor();
+        self.line((x + 1.0, zero_y), (x + width, zero_y), self.config.theme.grid());
+
+        let deltas = DeltaPlot::get_deltas(&self.d1.telemetry, &self.d2.telemetry);
+        let max_delta = DeltaPlot::get_delta_range(&deltas);
+        let half_height = (height / 2.0).floor();
+        let points: Vec<(f64, f64)> = self.d2.telemetry.iter().zip(&deltas)
+            .map(|(t, delta)| (x + width * distance_range.position(t),
+                y + half_height - half_height * delta / max_delta))
+            .collect();
+
+        // Each segment takes the color of the driver ahead at its end, so
+        // consecutive segments of the same color are joined into one line.
+        let (d1_color, d2_color) = (self.d1_color, self.d2_color);
+        let color = |delta: f64| if delta >= 0.0 { d1_color } else { d2_color };
+        let mut start = 0;
+        for end in 1..points.len() {
+            let is_last = end + 1 == points.len();
+            if is_last || color(deltas[end + 1]) != color(deltas[end]) {
+                let line_color = color(deltas[end]);
+                self.polyline(&points[start..=end], line_color, 1.0);
+                start = end;
+            }
+        }
+    }
+
+    fn driver_stats(&mut self, driver: u8, rect: Rect) {
+        let driver_data = if driver == 1 { self.d1 } else { self.d2 };
+        let config = self.config;
+        let final_frame = self.d1.telemetry.len().max(self.d2.telemetry.len());
+        let (x, y) = (rect.x as f64, rect.y as f64);
+        let text_x = x + config.padding_lr as f64;
+
+        let driver_height = self.text_cache.line_height(FontStyle::Bold, config.driver_font_size);
+        let team_height = self.text_cache.line_height(FontStyle::Regular, config.team_font_size);
+        let name_y = y + config.padding_tb_inner as f64;
+
+        self.text((text_x, name_y), FontStyle::Bold, config.driver_font_size,
+            Rgba::from(driver_data.driver.team_color), "start", &driver_data.driver.broadcast_name);
+        let team_y = name_y + driver_height as f64 + config.driver_team_margin as f64;
+        self.text((text_x, team_y), FontStyle::Regular, config.team_font_size, config.theme.foreground(), "start", 
+            &driver_data.driver.team_name);
+        if config.tyre_info {
+            self.tyre_info(driver_data, (text_x, team_y));
+        }
+
+        let lap_time_y = name_y + (driver_height + team_height + config.name_lap_speed_margin) as f64;
+        self.text((x + rect.width as f64 / 2.0, lap_time_y), FontStyle::Bold, config.lap_speed_font_size,
+            config.theme.foreground(), "middle", &HUD::get_str_time(driver_data.lap.lap_time));
+
+        let sector_height = self.text_cache.line_height(FontStyle::Timing, config.sector_font_size) as f64;
+        let sectors_y = y + rect.height as f64 - 3.0 * sector_height - config.sector_times_margin as f64;
+        for sector in 1..=3 {
+            self.text((text_x, sectors_y + (sector - 1) as f64 * sector_height), FontStyle::Timing,
+                config.sector_font_size, config.theme.foreground(), "start",
+                &HUD::get_sector_time_str(driver_data, sector, final_frame));
+        }
+    }
+
+    // Draws the compound badge and the tyre age after the team name starting at (x, y).
+    fn tyre_info(&mut self, driver_data: &CompleteDriverData, (x, y): (f64, f64)) {
+        let size = self.config.team_font_size;
+        let (badge_x, age_x) = self.hud.tyre_info_offsets(&driver_data.driver.team_name);
+
+        let age_x = match lap_compound(&driver_data.lap) {
+            Some(compound) => {
+                let badge_size = self.text_cache.line_height(FontStyle::Regular, size) as f64;
+                let letter_size = badge_letter_size(size);
+                let letter_height = self.text_cache.line_height(FontStyle::Bold, letter_size) as f64;
+                let center = (x + badge_x as f64 + badge_size / 2.0, y + badge_size / 2.0);
+
+                self.svg.push_str(&format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" {}/>\n",
+                    center.0, center.1, (badge_size - 1.0) / 2.0, Self::paint("fill", compound.color(self.config))));
+                self.text((center.0, center.1 - letter_height / 2.0), FontStyle::Bold, letter_size,
+                    compound.letter_color(self.config), "middle", compound.letter());
+                age_x
+            },
+            None => badge_x
+        };
+
+        if let Some(age) = tyre_age_str(&driver_data.lap) {
+            self.text((x + age_x as f64, y), FontStyle::Regular, size, self.config.theme.foreground(), "start", &age);
+        }
+    }
+
+    fn title(&mut self, text: &str, rect: Rect) {
+        let height = self.text_cache.line_height(FontStyle::Bold, self.config.driver_font_size) as f64;
+
+        self.text((rect.x as f64 + rect.width as f64 / 2.0, rect.y as f64 + (rect.height as f64 - height) / 2.0),
+            FontStyle::Bold, self.config.driver_font_size, self.config.theme.accent(), "middle", text);
+    }
+
+    fn header(&mut self, session: Option<&SessionData>, subtitle: Option<&str>, rect: Rect) {
+        let lines = Header::get_lines(session, subtitle, self.config);
+        let heights: Vec<f64> = lines.iter()
+            .map(|(style, size, _, _)| self.text_cache.line_height(*style, *size) as f64).collect();
+
+        let mut y = rect.y as f64 + (rect.height as f64 - heights.iter().sum::<f64>()) / 2.0;
+        for ((style, size, color, text), height) in lines.iter().zip(heights) {
+            self.text((rect.x as f64 + rect.width as f64 / 2.0, y), *style, *size, *color, "middle", text);
+            y += height;
+        }
+    }
+
+    fn watermark(&mut self, layout: &Layout) -> Result<(), String> {
+        let watermark = match &self.config.watermark {
+            Some(watermark) => watermark,
+            None => return Ok(())
+        };
+
+        let logo_size = match &watermark.image {
+            Some(path) => {
+                let (width, height) = image::image_dimensions(path)
+                    .map_err(|e| format!("Unable to load watermark image {}: {}", path, e))?;
+                let logo_height = watermark.image_height.unwrap_or(height);
+                Some((width as f64 * logo_height as f64 / height.max(1) as f64, logo_height as f64))
+            },
+            None => None
+        };
+        let text_size = watermark.text.as_ref().map(|text| {
+            let image = self.text_cache.get_text(FontStyle::Regular, watermark.font_size,
+                self.config.theme.foreground(), text);
+            (image.width() as f64, image.height() as f64)
+        });
+
+        let (logo_width, logo_height) = logo_size.unwrap_or((0.0, 0.0));
+        let (text_width, text_height) = text_size.unwrap_or((0.0, 0.0));
+        let gap = if logo_size.is_some() && text_size.is_some() { watermark.margin as f64 } else { 0.0 };
+        let (width, height) = (logo_width + gap + text_width, logo_height.max(text_height));
+        let margin = watermark.margin as f64;
+
+        let x = match watermark.corner {
+            Corner::TopLeft | Corner::BottomLeft => margin,
+            Corner::TopRight | Corner::BottomRight => layout.width as f64 - margin - width
+        };
+        let y = match watermark.corner {
+            Corner::TopLeft | Corner::TopRight => margin,
+            Corner::BottomLeft | Corner::BottomRight => layout.height as f64 - margin - height
+        };
+
+        self.svg.push_str(&format!("<g opacity=\"{}\">\n", watermark.opacity));
+        if let Some(path) = &watermark.image {
+            self.svg.push_str(&format!(
+                "<image href=\"{}\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" preserveAspectRatio=\"none\"/>\n",
+                Self::escape(path), x, y + (height - logo_height) / 2.0, logo_width, logo_height));
+        }
+        if let Some(text) = &watermark.text {
+            self.text((x + logo_width + gap, y + (height - text_height) / 2.0), FontStyle::Regular,
+                watermark.font_size, self.config.theme.foreground(), "start", text);
+        }
+        self.svg.push_str("</g>\n");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::gif_generator::{render_svg, test_utils::lap, FontConfig, RenderOptions};
+
+    // Writes the built-in regular font renamed to the family "Mock Sans".
+    fn mock_font() -> String {
+        let mut data = include_bytes!("../../static/fonts/OpenSans-Regular.ttf").to_vec();
+        let utf16 = |text: &str| text.encode_utf16().flat_map(u16::to_be_bytes).collect::<Vec<u8>>();
+
+        for (from, to) in [(utf16("Open Sans"), utf16("Mock Sans")), (b"Open Sans".to_vec(), b"Mock Sans".to_vec())] {
+            for start in 0..=data.len() - from.len() {
+                if data[start..start + from.len()] == from[..] {
+                    data[start..start + to.len()].copy_from_slice(&to);
+                }
+            }
+        }
+
+        let path = env::temp_dir().join("svg_export_mock_sans.ttf");
+        fs::write(&path, data).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn text_uses_the_configured_font_families() {
+        let speeds = [200; 20];
+        let mut options = RenderOptions::default();
+        options.config.fonts = FontConfig { regular: Some(mock_font()), ..FontConfig::default() };
+
+        let svg = render_svg(lap(&speeds, 50), lap(&speeds, 60), &options).unwrap();
+
+        let team_name = svg.lines().find(|line| line.ends_with(">Test Racing</text>")).unwrap();
+        assert!(team_name.contains("font-family=\"'Mock Sans', 'Open Sans', sans-serif\""), "{}", team_name);
+        assert!(svg.contains("font-family=\"'Open Sans', sans-serif\" font-weight=\"700\""));
+    }
+}
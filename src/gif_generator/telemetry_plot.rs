@@ -1,21 +1,49 @@
-use std::cmp::max;
+use std::{cmp::max, rc::Rc};
 
-use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
 use image::{imageops::overlay, Rgba, RgbaImage};
-use imageproc::{definitions::HasWhite, 
-    drawing::{draw_line_segment_mut, draw_text_mut, text_size}};
+use imageproc::drawing::draw_line_segment_mut;
 use image::imageops::rotate270;
 
-use crate::data_fetcher::{CompleteDriverData, DriverTelemetryData};
+use crate::data_fetcher::{CompleteDriverData, DriverTelemetryData, SessionData};
 
-use super::{TELEMETRY_LABEL_FONT_SIZE, TELEMETRY_LABEL_MARGIN, 
-    TELEMETRY_PLOT_AXES_LABELS_MARGIN, TELEMETRY_PLOT_HEIGHT, TELEMETRY_PLOT_WIDTH, TRANSPARENT};
+use super::{draw_antialiased_line_mut, trace_width, Compositor, DistanceRange, FontStyle, RenderConfig, TextCache, TRANSPARENT};
+
+/// Alpha of the gridlines, which are drawn in the theme's grid color.
+pub const GRIDLINE_ALPHA: u8 = 80;
+/// Length of the tick marks outside of the axes.
+pub const TICK_LENGTH: u32 = 3;
+
+// Speed in km/h by which a driver has to be faster on both sides of their
+// slowest point in a corner, before slowing down further, for it to count
+// as an apex.
+const MIN_APEX_DROP: i32 = 15;
+
+// Alpha of the progress cursors, which are drawn in the drivers' colors.
+const CURSOR_ALPHA: u8 = 110;
+
+/// A labelled tick at `position` along an axis, from 0 at the origin to 1 at its end.
+pub struct Tick {
+    pub position: f64,
+    pub label: String
+}
+
+/// Caption and ticks of a plot axis.
+pub struct Axis {
+    pub label: String,
+    pub ticks: Vec<Tick>
+}
 
 struct TelemetryPlotDriverData<'a> {
     data: &'a CompleteDriverData,
     buffer: RgbaImage,
     color: Rgba<u8>,
-    current_point: Option<(u32, u32)>,
+    current_point: Option<(f32, f32)>,
+    // Labels of the apex speeds with their position, by the frame of the apex.
+    apex_labels: Vec<(usize, Rc<RgbaImage>, (i64, i64))>,
+    // Vertical line at the driver's current distance and the dot at their
+    // current speed, shown until they finish.
+    cursor: RgbaImage,
+    marker: RgbaImage
 }
 
 pub struct TelemetryPlot<'a> {
@@ -23,102 +51,115 @@ pub struct TelemetryPlot<'a> {
     d2: TelemetryPlotDriverData<'a>,
     
     base_buffer: RgbaImage,
-    max_speed: i32,
+    padding: u32,
+    axes_labels_margin: u32,
+    // Speed at the top of the plot.
+    speed_scale: f64,
+    distance_range: DistanceRange,
+    progress_cursor: bool,
     current_frame: usize
 }
 
 impl <'a> TelemetryPlot<'a> {
     pub fn new(d1_complete_data: &'a CompleteDriverData, d2_complete_data: &'a CompleteDriverData, 
-        d1_color: Rgba<u8>, d2_color: Rgba<u8>, font: &FontRef) -> TelemetryPlot<'a> {
-        let d1 = TelemetryPlotDriverData {
-            data: d1_complete_data, 
-            color: d1_color, 
-            buffer: RgbaImage::from_pixel(TELEMETRY_PLOT_WIDTH - TELEMETRY_PLOT_AXES_LABELS_MARGIN, 
-                TELEMETRY_PLOT_HEIGHT - TELEMETRY_PLOT_AXES_LABELS_MARGIN, TRANSPARENT),
-            current_point: None, 
-        };
+        (d1_color, d2_color): (Rgba<u8>, Rgba<u8>), session: Option<&SessionData>, text_cache: &TextCache, 
+        config: &RenderConfig, (width, height): (u32, u32)) -> TelemetryPlot<'a> {
+        let axes_labels_margin = config.telemetry_plot_axes_labels_margin;
+        let (base_width, base_height) = plot_base_size(config, width, height);
+        let plot_width = base_width - axes_labels_margin;
+        let plot_height = base_height - axes_labels_margin;
 
-        let d2= TelemetryPlotDriverData {
-            data: d2_complete_data, 
-            color: d2_color, 
-            buffer: RgbaImage::from_pixel(TELEMETRY_PLOT_WIDTH - TELEMETRY_PLOT_AXES_LABELS_MARGIN, 
-                TELEMETRY_PLOT_HEIGHT - TELEMETRY_PLOT_AXES_LABELS_MARGIN, TRANSPARENT),
-            current_point: None, 
+        let max_speed = Self::get_max_speed(d1_complete_data, d2_complete_data);
+        let (speed_scale, speed_axis) = speed_axis(text_cache, config, max_speed, plot_height);
+        let distance_range = DistanceRange::new(config, d1_complete_data, d2_complete_data);
+        let distance_axis = distance_axis(text_cache, config, distance_range, plot_width);
+
+        let mut base_buffer = draw_plot_base(text_cache, config, base_width, base_height, &distance_axis, &speed_axis);
+        if config.corner_markers {
+            let labels_start = corner_labels_start(text_cache, config, &speed_axis);
+            draw_corner_markers(&mut base_buffer, text_cache, config, &corner_ticks(session, distance_range), labels_start);
+        }
+
+        let driver = |data: &'a CompleteDriverData, color: Rgba<u8>, slot: u32| {
+            let buffer = RgbaImage::from_pixel(plot_width, plot_height, TRANSPARENT);
+            let apex_labels = if config.apex_speeds { apex_indices(&data.telemetry) } else { Vec::new() }
+                .into_iter()
+                .filter_map(|frame| {
+                    let point = Self::get_point(data, speed_scale, distance_range, plot_width, plot_height, frame)?;
+                    let label = text_cache.get_text(FontStyle::Regular, config.telemetry_label_font_size, 
+                        Rgba([color[0], color[1], color[2], 255]), &config.units.round_speed(data.telemetry[frame].speed).to_string());
+                    let position = apex_label_position(config, point, label.dimensions(), (plot_width, plot_height), slot);
+                    Some((frame, label, position))
+                })
+                .collect();
+
+            let cursor = RgbaImage::from_pixel(1, plot_height, Rgba([color[0], color[1], color[2], CURSOR_ALPHA]));
+
+            TelemetryPlotDriverData { data, color, buffer, current_point: None, apex_labels, cursor, 
+                marker: Self::marker(color, trace_width(config.thickness) + 2.0) }
         };
 
-        TelemetryPlot { d1, d2, 
-            max_speed: Self::get_max_speed(d1_complete_data, d2_complete_data),
-            base_buffer: Self::draw_base(font),
+        TelemetryPlot { 
+            d1: driver(d1_complete_data, d1_color, 0), 
+            d2: driver(d2_complete_data, d2_color, 1), 
+            speed_scale,
+            distance_range,
+            progress_cursor: config.progress_cursor,
+            base_buffer,
+            padding: config.padding,
+            axes_labels_margin,
             current_frame: 0
         }
     }
 
     pub fn draw_next_frame(&mut self) {
-        Self::draw_telemetry(self.max_speed, &mut self.d1, self.current_frame);
-        Self::draw_telemetry(self.max_speed, &mut self.d2, self.current_frame);
+        Self::draw_telemetry(self.speed_scale, self.distance_range, &mut self.d1, self.current_frame);
+        Self::draw_telemetry(self.speed_scale, self.distance_range, &mut self.d2, self.current_frame);
 
         self.current_frame += 1;
     }
 
-    pub fn get_telemetry_plot(&self) -> RgbaImage {
-        let mut buffer = self.base_buffer.clone();
-        overlay(&mut buffer, &self.d1.buffer, TELEMETRY_PLOT_AXES_LABELS_MARGIN as i64, 0);
-        overlay(&mut buffer, &self.d2.buffer, TELEMETRY_PLOT_AXES_LABELS_MARGIN as i64, 0);
-
-        buffer
-    }
+    pub fn compose(&self, compositor: &mut Compositor, x: i64, y: i64) {
+        let x = x + self.padding as i64;
+        let y = y + self.padding as i64;
+        let plot_x = x + self.axes_labels_margin as i64;
 
-    fn draw_axes(buffer: &mut RgbaImage) {
-        draw_line_segment_mut(buffer, 
-            (TELEMETRY_PLOT_AXES_LABELS_MARGIN as f32, (TELEMETRY_PLOT_HEIGHT - TELEMETRY_PLOT_AXES_LABELS_MARGIN) as f32), 
-            (TELEMETRY_PLOT_WIDTH as f32, (TELEMETRY_PLOT_HEIGHT - TELEMETRY_PLOT_AXES_LABELS_MARGIN) as f32), 
-            Rgba::white());
-    
-        draw_line_segment_mut(buffer, 
-            (TELEMETRY_PLOT_AXES_LABELS_MARGIN as f32, 0.0), 
-            (TELEMETRY_PLOT_AXES_LABELS_MARGIN as f32, (TELEMETRY_PLOT_HEIGHT - TELEMETRY_PLOT_AXES_LABELS_MARGIN) as f32), 
-            Rgba::white());
-    }
+        compositor.blend(&self.base_buffer, x, y);
+        compositor.blend(&self.d1.buffer, plot_x, y);
+        compositor.blend(&self.d2.buffer, plot_x, y);
 
-    fn draw_distance_label(buffer: &mut RgbaImage, font: &FontRef, scale: PxScale) {
-        let (distance_label_width, _) = text_size(scale, font, "DISTANCE");
-        let distance_label_x = (TELEMETRY_PLOT_WIDTH - TELEMETRY_PLOT_AXES_LABELS_MARGIN) / 2 
-            + TELEMETRY_PLOT_AXES_LABELS_MARGIN - distance_label_width / 2;
-        let distance_label_y = TELEMETRY_PLOT_HEIGHT - TELEMETRY_PLOT_AXES_LABELS_MARGIN + TELEMETRY_LABEL_MARGIN;
-        draw_text_mut(buffer, Rgba::white(), 
-            distance_label_x as i32, distance_label_y as i32, scale, font, "DISTANCE");
+        if self.progress_cursor {
+            for driver in [&self.d1, &self.d2] {
+                self.compose_cursor(compositor, driver, plot_x, y);
+            }
+        }
     }
 
-    fn draw_speed_label(buffer: &mut RgbaImage, font: &FontRef, scale: PxScale) {
-        let (speed_label_width, _) = text_size(scale, font, "SPEED");
-        let height = font.as_scaled(scale).height().ceil() as u32;
-    
-        let mut speed_label = RgbaImage::from_pixel(speed_label_width, height, TRANSPARENT);
-        draw_text_mut(&mut speed_label, Rgba::white(), 0, 0, scale, font, "SPEED");
-
-        let speed_label_rotated = rotate270(&speed_label);
-    
-        let speed_label_x = TELEMETRY_PLOT_AXES_LABELS_MARGIN - TELEMETRY_LABEL_MARGIN - height;
-        let speed_label_y = (TELEMETRY_PLOT_HEIGHT - TELEMETRY_PLOT_AXES_LABELS_MARGIN) / 2 - speed_label_width / 2;
+    // The driver's current point is the last one drawn, so the cursor is
+    // shown while the frame drawn last is within their lap.
+    fn compose_cursor(&self, compositor: &mut Compositor, driver: &TelemetryPlotDriverData, x: i64, y: i64) {
+        let Some((point_x, point_y)) = driver.current_point else { return };
+        if self.current_frame > driver.data.telemetry.len() {
+            return;
+        }
 
-        overlay(buffer, &speed_label_rotated, speed_label_x as i64, speed_label_y as i64);
+        let radius = (driver.marker.width() / 2) as i64;
+        compositor.blend(&driver.cursor, x + point_x.round() as i64, y);
+        compositor.blend(&driver.marker, x + point_x.round() as i64 - radius, y + point_y.round() as i64 - radius);
     }
 
-    fn draw_base(font: &FontRef) -> RgbaImage {
-        let mut buffer = 
-            RgbaImage::from_pixel(TELEMETRY_PLOT_WIDTH, TELEMETRY_PLOT_HEIGHT, TRANSPARENT);
-    
-        let scale = font.pt_to_px_scale(TELEMETRY_LABEL_FONT_SIZE as f32)
-            .unwrap_or(PxScale::from(TELEMETRY_LABEL_FONT_SIZE as f32));
+    // A dot of the given diameter centered in an image of odd size.
+    fn marker(color: Rgba<u8>, diameter: f32) -> RgbaImage {
+        let size = 2 * (diameter / 2.0).ceil() as u32 + 1;
+        let center = (size / 2) as f32;
+        let mut marker = RgbaImage::from_pixel(size, size, TRANSPARENT);
+        draw_antialiased_line_mut(&mut marker, (center, center), (center, center), 
+            Rgba([color[0], color[1], color[2], 255]), diameter);
 
-        Self::draw_distance_label(&mut buffer, font, scale);
-        Self::draw_speed_label(&mut buffer, font, scale);
-        Self::draw_axes(&mut buffer);
-    
-        buffer
+        marker
     }
 
-    fn get_max_speed(d1: &CompleteDriverData, d2: &CompleteDriverData) -> i32 {
+    pub fn get_max_speed(d1: &CompleteDriverData, d2: &CompleteDriverData) -> i32 {
         let get_speed = |t: &DriverTelemetryData| -> i32 { t.speed };
         let speed1 = d1.telemetry.iter().map(get_speed).max().unwrap();
         let speed2 = d2.telemetry.iter().map(get_speed).max().unwrap();
@@ -126,28 +167,352 @@ impl <'a> TelemetryPlot<'a> {
         max(speed1, speed2)
     }
 
-    fn get_point(driver: &TelemetryPlotDriverData, max_speed: i32, width: u32, height: u32, current_frame: usize) -> Option<(u32, u32)> {
-        let telemetry = driver.data.telemetry.get(current_frame)?;
+    fn get_point(data: &CompleteDriverData, speed_scale: f64, distance_range: DistanceRange, 
+        width: u32, height: u32, current_frame: usize) -> Option<(f32, f32)> {
+        let telemetry = data.telemetry.get(current_frame)?;
     
-        let x = (width as f64 * telemetry.relative_distance) as u32;
-        let y = height - (height as f64 * (telemetry.speed as f64 / max_speed as f64)) as u32;
+        let x = (width as f64 * distance_range.position(telemetry)) as f32;
+        let y = (height as f64 * (1.0 - telemetry.speed as f64 / speed_scale)) as f32;
     
         Some((x, y))
     }
 
-    fn draw_telemetry(max_speed: i32, driver: &mut TelemetryPlotDriverData, current_frame: usize) {
-        if let Some(new_point) = Self::get_point(driver, max_speed, driver.buffer.width(), driver.buffer.height(), current_frame) {
+    fn draw_telemetry(speed_scale: f64, distance_range: DistanceRange, driver: &mut TelemetryPlotDriverData, current_frame: usize) {
+        if let Some(new_point) = Self::get_point(driver.data, speed_scale, distance_range, 
+            driver.buffer.width(), driver.buffer.height(), current_frame) {
             if let Some(prev_point) = driver.current_point {
-                let start = (prev_point.0 as f32, prev_point.1 as f32);
-                let end = (new_point.0 as f32, new_point.1 as f32);
-                draw_line_segment_mut(&mut driver.buffer, start, end, driver.color);
+                draw_antialiased_line_mut(&mut driver.buffer, prev_point, new_point, driver.color, 1.0);
             }
             driver.current_point = Some(new_point);
         }
+
+        for (_, label, (x, y)) in driver.apex_labels.iter().filter(|(frame, _, _)| *frame == current_frame) {
+            overlay(&mut driver.buffer, label.as_ref(), *x, *y);
+        }
     }
 }
 
+// Size of the plot including axes labels, inside a panel of the given size.
+pub fn plot_base_size(config: &RenderConfig, width: u32, height: u32) -> (u32, u32) {
+    (width - 2 * config.padding, height - 2 * config.padding)
+}
+
+// Rounds the step between at most `max_ticks` ticks spanning `range` up to
+// 1, 2 or 5 times a power of ten.
+fn nice_step(range: f64, max_ticks: u32) -> f64 {
+    let raw_step = range / max_ticks.max(1) as f64;
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+
+    [1.0, 2.0, 5.0, 10.0].iter().map(|multiple| multiple * magnitude)
+        .find(|step| *step >= raw_step)
+        .unwrap_or(10.0 * magnitude)
+}
+
+// Ticks at the multiples of `step` between `start` and `end`, labelled with
+// as many decimals as the step needs.
+fn ticks((start, end): (f64, f64), step: f64) -> Vec<Tick> {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let first = (start / step - 1e-9).ceil() as i64;
+    let last = (end / step + 1e-9).floor() as i64;
+
+    (first..=last).map(|i| {
+        let value = i as f64 * step;
+        Tick { position: (value - start) / (end - start), label: format!("{:.*}", decimals, value) }
+    }).collect()
+}
+
+/// Distance axis of the plots in the configured units, or in percent of the
+/// lap if the laps have no distances in metres, with ticks at least four
+/// lines of text apart.
+pub fn distance_axis(text_cache: &TextCache, config: &RenderConfig, distance_range: DistanceRange, plot_width: u32) -> Axis {
+    let spacing = 4 * text_cache.line_height(FontStyle::Regular, config.telemetry_label_font_size);
+    let DistanceRange { start, end, metres } = distance_range;
+    let (range, unit) = if metres {
+        ((config.units.distance(start), config.units.distance(end)), config.units.distance_unit())
+    } else {
+        ((start * 100.0, end * 100.0), "%")
+    };
+    let step = nice_step(range.1 - range.0, plot_width / spacing.max(1));
+
+    Axis { label: format!("DISTANCE ({})", unit.to_uppercase()), ticks: ticks(range, step) }
+}
+
+/// Returns the speed in km/h at the top of the speed axis, the next tick
+/// above the highest speed, and the axis in the configured units with ticks
+/// at least two lines of text apart.
+pub fn speed_axis(text_cache: &TextCache, config: &RenderConfig, max_speed: i32, plot_height: u32) -> (f64, Axis) {
+    let units = config.units;
+    let spacing = 2 * text_cache.line_height(FontStyle::Regular, config.telemetry_label_font_size);
+    let max_speed = units.speed(max_speed.max(1) as f64);
+    let step = nice_step(max_speed, plot_height / spacing.max(1));
+    let speed_scale = (max_speed / step).ceil() * step;
+
+    let label = format!("SPEED ({})", units.speed_unit().to_uppercase());
+    (speed_scale / units.speed(1.0), Axis { label, ticks: ticks((0.0, speed_scale), step) })
+}
+
+/// Returns markers of the session's corners within the distance range,
+/// labelled with their turn number.
+pub fn corner_ticks(session: Option<&SessionData>, distance_range: DistanceRange) -> Vec<Tick> {
+    let corners = session.map(|session| session.corners.as_slice()).unwrap_or_default();
+
+    corners.iter()
+        .filter_map(|corner| Some(Tick {
+            position: distance_range.corner_position(corner)?,
+            label: format!("T{}{}", corner.number, corner.letter)
+        }))
+        .collect()
+}
+
+/// Returns the indices of the driver's apexes: the slowest samples from
+/// which the speed rises by `MIN_APEX_DROP` on both sides before dropping
+/// any lower. Of equally slow samples the last one is the apex.
+pub fn apex_indices(telemetry: &[DriverTelemetryData]) -> Vec<usize> {
+    let speeds: Vec<i32> = telemetry.iter().map(|t| t.speed).collect();
+
+    (0..speeds.len()).filter(|&i| {
+        let speed = speeds[i];
+        let rises_before = speeds[..i].iter().rev().take_while(|s| **s >= speed).any(|s| *s >= speed + MIN_APEX_DROP);
+        let rises_after = speeds[i + 1..].iter().take_while(|s| **s > speed).any(|s| *s >= speed + MIN_APEX_DROP);
+
+        rises_before && rises_after
+    }).collect()
+}
+
+/// Top left corner of an apex speed label below the apex at `point`, in a
+/// plot area of the given size. The second driver's labels (`slot` 1) go
+/// below the first driver's ones.
+pub fn apex_label_position(config: &RenderConfig, (x, y): (f32, f32), (label_width, label_height): (u32, u32), 
+    (width, height): (u32, u32), slot: u32) -> (i64, i64) {
+    let label_x = (x.round() as i64 - label_width as i64 / 2).clamp(0, (width as i64 - label_width as i64).max(0));
+    let label_y = y.round() as i64 + config.telemetry_label_margin as i64 + (slot * label_height) as i64;
+
+    (label_x, label_y.min(height as i64 - label_height as i64))
+}
+
+/// Position of the line and the top left corner of the label of a corner
+/// marker, in a plot base of the given width. Labels are at the top of the
+/// plot, centered on their line.
+pub fn corner_marker_position(config: &RenderConfig, width: u32, tick: &Tick, label_width: u32) -> (i64, (i64, i64)) {
+    let margin = config.telemetry_plot_axes_labels_margin;
+    let x = (margin as i64 + (tick.position * (width - margin) as f64).round() as i64).min(width as i64 - 1);
 
+    (x, ((x - label_width as i64 / 2).min(width as i64 - label_width as i64).max(margin as i64), 0))
+}
 
+/// Left end of the row of corner labels, right of the y-axis tick labels.
+pub fn corner_labels_start(text_cache: &TextCache, config: &RenderConfig, y_axis: &Axis) -> i64 {
+    let widest_tick_label = y_axis.ticks.iter()
+        .map(|tick| text_cache.get_text(FontStyle::Regular, config.telemetry_label_font_size, 
+            config.theme.foreground(), &tick.label).width())
+        .max().unwrap_or(0);
 
+    (config.telemetry_plot_axes_labels_margin + 2 * config.telemetry_label_margin + widest_tick_label) as i64
+}
+
+// Draws a vertical line below a label for every corner. Labels which would
+// start before `labels_start` or overlap the previous one are left out.
+fn draw_corner_markers(buffer: &mut RgbaImage, text_cache: &TextCache, config: &RenderConfig, ticks: &[Tick], 
+    labels_start: i64) {
+    let axis_y = buffer.height() - config.telemetry_plot_axes_labels_margin;
+    let Rgba([r, g, b, _]) = config.theme.grid();
+    let mut previous_label_end = labels_start;
+
+    for tick in ticks {
+        let label = text_cache.get_text(FontStyle::Regular, config.telemetry_label_font_size, config.theme.grid(), &tick.label);
+        let (x, (label_x, label_y)) = corner_marker_position(config, buffer.width(), tick, label.width());
+
+        for y in label.height()..axis_y {
+            buffer.put_pixel(x as u32, y, Rgba([r, g, b, GRIDLINE_ALPHA]));
+        }
+        if label_x >= previous_label_end {
+            overlay(buffer, label.as_ref(), label_x, label_y);
+            previous_label_end = label_x + (label.width() + config.telemetry_label_margin) as i64;
+        }
+    }
+}
 
+fn draw_axes(buffer: &mut RgbaImage, margin: u32, color: Rgba<u8>) {
+    let margin = margin as f32;
+    let width = buffer.width() as f32;
+    let height = buffer.height() as f32;
+
+    draw_line_segment_mut(buffer, 
+        (margin, height - margin), 
+        (width, height - margin), 
+        color);
+
+    draw_line_segment_mut(buffer, 
+        (margin, 0.0), 
+        (margin, height - margin), 
+        color);
+}
+
+fn draw_x_label(buffer: &mut RgbaImage, text_cache: &TextCache, config: &RenderConfig, label: &str) {
+    let margin = config.telemetry_plot_axes_labels_margin;
+
+    let x_label = text_cache.get_text(FontStyle::Regular, config.telemetry_label_font_size, 
+        config.theme.foreground(), label);
+    let x_label_x = (buffer.width() - margin) as i64 / 2 
+        + margin as i64 - x_label.width() as i64 / 2;
+    let x_label_y = buffer.height() as i64 - x_label.height() as i64;
+    overlay(buffer, x_label.as_ref(), x_label_x, x_label_y);
+}
+
+fn draw_y_label(buffer: &mut RgbaImage, text_cache: &TextCache, config: &RenderConfig, label: &str) {
+    let margin = config.telemetry_plot_axes_labels_margin;
+
+    let y_label = text_cache.get_text(FontStyle::Regular, config.telemetry_label_font_size, 
+        config.theme.foreground(), label);
+    let y_label_width = y_label.width();
+    let height = y_label.height();
+
+    let y_label_rotated = rotate270(y_label.as_ref());
+
+    let y_label_x = margin as i64 - config.telemetry_label_margin as i64 - height as i64;
+    let y_label_y = (buffer.height() - margin) as i64 / 2 - y_label_width as i64 / 2;
+
+    overlay(buffer, &y_label_rotated, y_label_x, y_label_y);
+}
+
+/// Position of the tick mark and the top left corner of the label of an
+/// x-axis tick, in a plot base of the given size. Labels are centered under
+/// their tick but kept inside the plot base.
+pub fn x_tick_position(config: &RenderConfig, (width, height): (u32, u32), tick: &Tick, label_width: u32) -> (i64, (i64, i64)) {
+    let margin = config.telemetry_plot_axes_labels_margin;
+    let x = margin as i64 + (tick.position * (width - margin) as f64).round() as i64;
+    let label_x = (x - label_width as i64 / 2).min(width as i64 - label_width as i64).max(0);
+
+    (x.min(width as i64 - 1), (label_x, (height - margin) as i64 + 1))
+}
+
+/// Position of the gridline and the top left corner of the label of a
+/// y-axis tick. Labels are inside the plot, right of the axis and centered
+/// on their gridline, but kept between the top of the plot and the x-axis.
+pub fn y_tick_position(config: &RenderConfig, height: u32, tick: &Tick, label_height: u32) -> (i64, (i64, i64)) {
+    let margin = config.telemetry_plot_axes_labels_margin;
+    let plot_height = (height - margin) as i64;
+    let y = ((1.0 - tick.position) * plot_height as f64).round() as i64;
+    let label_y = (y - label_height as i64 / 2).clamp(0, (plot_height - label_height as i64).max(0));
+
+    (y, ((margin + config.telemetry_label_margin) as i64, label_y))
+}
+
+fn draw_x_ticks(buffer: &mut RgbaImage, text_cache: &TextCache, config: &RenderConfig, ticks: &[Tick]) {
+    let axis_y = buffer.height() - config.telemetry_plot_axes_labels_margin;
+
+    for tick in ticks {
+        let label = text_cache.get_text(FontStyle::Regular, config.telemetry_label_font_size, 
+            config.theme.foreground(), &tick.label);
+        let (x, (label_x, label_y)) = x_tick_position(config, buffer.dimensions(), tick, label.width());
+
+        for y in axis_y + 1..=(axis_y + TICK_LENGTH).min(buffer.height() - 1) {
+            buffer.put_pixel(x as u32, y, config.theme.axis());
+        }
+        overlay(buffer, label.as_ref(), label_x, label_y);
+    }
+}
+
+// Draws the y-axis ticks with a gridline from the label to the end of the
+// plot. The gridline of a tick at the origin would be the x-axis itself.
+fn draw_y_ticks(buffer: &mut RgbaImage, text_cache: &TextCache, config: &RenderConfig, ticks: &[Tick]) {
+    let margin = config.telemetry_plot_axes_labels_margin;
+    let Rgba([r, g, b, _]) = config.theme.grid();
+
+    for tick in ticks {
+        let label = text_cache.get_text(FontStyle::Regular, config.telemetry_label_font_size, 
+            config.theme.foreground(), &tick.label);
+        let (y, (label_x, label_y)) = y_tick_position(config, buffer.height(), tick, label.height());
+
+        for x in margin.saturating_sub(TICK_LENGTH)..margin {
+            buffer.put_pixel(x, y as u32, config.theme.axis());
+        }
+        if tick.position > 0.0 {
+            let gridline_start = label_x as u32 + label.width() + config.telemetry_label_margin;
+            for x in gridline_start..buffer.width() {
+                buffer.put_pixel(x, y as u32, Rgba([r, g, b, GRIDLINE_ALPHA]));
+            }
+        }
+        overlay(buffer, label.as_ref(), label_x, label_y);
+    }
+}
+
+// Draws the axes with their captions and ticks, shared by all plot panels.
+pub fn draw_plot_base(text_cache: &TextCache, config: &RenderConfig, width: u32, height: u32, 
+    x_axis: &Axis, y_axis: &Axis) -> RgbaImage {
+    let mut buffer = RgbaImage::from_pixel(width, height, TRANSPARENT);
+
+    draw_x_label(&mut buffer, text_cache, config, &x_axis.label);
+    draw_y_label(&mut buffer, text_cache, config, &y_axis.label);
+    draw_y_ticks(&mut buffer, text_cache, config, &y_axis.ticks);
+    draw_x_ticks(&mut buffer, text_cache, config, &x_axis.ticks);
+    draw_axes(&mut buffer, config.telemetry_plot_axes_labels_margin, config.theme.axis());
+
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gif_generator::test_utils::lap;
+
+    #[test]
+    fn steps_are_nice_numbers() {
+        assert_eq!(nice_step(100.0, 10), 10.0);
+        assert_eq!(nice_step(100.0, 4), 50.0);
+        assert_eq!(nice_step(5296.0, 6), 1000.0);
+        assert_eq!(nice_step(350.0, 8), 50.0);
+        assert_eq!(nice_step(1.0, 3), 0.5);
+        assert_eq!(nice_step(0.07, 5), 0.02);
+        assert_eq!(nice_step(100.0, 0), 100.0);
+    }
+
+    #[test]
+    fn ticks_are_at_multiples_of_the_step() {
+        let ticks = ticks((0.0, 400.0), 100.0);
+
+        assert_eq!(ticks.iter().map(|tick| tick.label.as_str()).collect::<Vec<_>>(), vec!["0", "100", "200", "300", "400"]);
+        assert_eq!(ticks.iter().map(|tick| tick.position).collect::<Vec<_>>(), vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn ticks_start_within_the_range() {
+        let ticks = ticks((1050.0, 2980.0), 500.0);
+
+        assert_eq!(ticks.iter().map(|tick| tick.label.as_str()).collect::<Vec<_>>(), vec!["1500", "2000", "2500"]);
+        assert!((ticks[0].position - 450.0 / 1930.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ticks_have_the_decimals_of_the_step() {
+        let labels: Vec<String> = ticks((0.1, 0.35), 0.05).into_iter().map(|tick| tick.label).collect();
+
+        assert_eq!(labels, vec!["0.10", "0.15", "0.20", "0.25", "0.30", "0.35"]);
+    }
+
+    fn apexes(speeds: &[i32]) -> Vec<usize> {
+        apex_indices(&lap(speeds, 50).telemetry)
+    }
+
+    #[test]
+    fn apexes_are_the_slowest_points_of_corners() {
+        assert_eq!(apexes(&[300, 200, 150, 160, 250, 300, 180, 300]), vec![2, 6]);
+    }
+
+    #[test]
+    fn shallow_dips_are_no_apexes() {
+        assert_eq!(apexes(&[250, 245, 240, 248, 252]), Vec::<usize>::new());
+        assert_eq!(apexes(&[250, 240, 235, 250, 260]), vec![2]);
+    }
+
+    #[test]
+    fn the_last_of_equally_slow_samples_is_the_apex() {
+        assert_eq!(apexes(&[300, 120, 120, 120, 200]), vec![3]);
+    }
+
+    #[test]
+    fn the_ends_of_the_lap_are_no_apexes() {
+        assert_eq!(apexes(&[100, 200, 300, 200, 100]), Vec::<usize>::new());
+        assert_eq!(apex_indices(&[]), Vec::<usize>::new());
+    }
+}
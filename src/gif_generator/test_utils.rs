@@ -0,0 +1,45 @@
+use crate::data_fetcher::{CompleteDriverData, DriverData, DriverTelemetryData, LapData};
+
+pub const SESSION_START: i64 = 3_600_000;
+pub const SAMPLE_SPACING: f64 = 10.0;
+
+/// Returns a lap with a sample of every given speed, `sample_time` ms and
+/// 10 metres apart, along the x-axis.
+pub fn lap(speeds: &[i32], sample_time: i64) -> CompleteDriverData {
+    let last = (speeds.len() - 1).max(1) as f64;
+
+    let telemetry = speeds.iter().enumerate().map(|(i, &speed)| DriverTelemetryData {
+        x: (i as f64 * SAMPLE_SPACING) as i32,
+        y: 0,
+        session_time: SESSION_START + i as i64 * sample_time,
+        speed,
+        relative_distance: i as f64 / last,
+        distance: Some(i as f64 * SAMPLE_SPACING),
+        throttle: Some(100.0),
+        brake: Some(false)
+    }).collect();
+
+    let lap_time = (speeds.len() as i64 - 1) * sample_time;
+    let sector_time = lap_time / 3;
+
+    let lap = LapData {
+        lap_time: lap_time as i32,
+        sector1_time: sector_time as i32,
+        sector2_time: sector_time as i32,
+        sector3_time: (lap_time - 2 * sector_time) as i32,
+        sector1_session_time: SESSION_START + sector_time,
+        sector2_session_time: SESSION_START + 2 * sector_time,
+        sector3_session_time: SESSION_START + lap_time,
+        compound: None,
+        tyre_life: None,
+        fresh_tyre: None
+    };
+
+    let driver = DriverData {
+        broadcast_name: String::from("D RIVER"),
+        team_name: String::from("Test Racing"),
+        team_color: [54, 113, 198, 255]
+    };
+
+    CompleteDriverData { telemetry, lap, driver }
+}
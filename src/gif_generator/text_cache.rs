@@ -0,0 +1,143 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use ab_glyph::{point, Font, FontArc, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::draw_text_mut;
+
+use super::{Fonts, TRANSPARENT};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FontStyle {
+    Regular,
+    Bold,
+    /// The sector times and the cards' timing columns.
+    Timing
+}
+
+type TextKey = (FontStyle, u32, [u8; 4], String);
+
+// Rasterised text is cached by (font, size, color, string), so labels which
+// do not change between frames are drawn only once and then composited.
+pub struct TextCache<'a> {
+    fonts: &'a Fonts,
+
+    cache: RefCell<HashMap<TextKey, Rc<RgbaImage>>>
+}
+
+impl <'a> TextCache<'a> {
+    pub fn new(fonts: &'a Fonts) -> TextCache<'a> {
+        TextCache { fonts, cache: RefCell::new(HashMap::new()) }
+    }
+
+    fn scale(font: &FontArc, size: u32) -> PxScale {
+        font.pt_to_px_scale(size as f32)
+            .unwrap_or(PxScale::from(size as f32))
+    }
+
+    /// Height of a line of text in the style's own font. Text drawn with
+    /// taller fallback fonts can be higher, see `get_text`.
+    pub fn line_height(&self, style: FontStyle, size: u32) -> u32 {
+        let font = self.fonts.primary(style);
+        font.as_scaled(Self::scale(font, size)).height().ceil() as u32
+    }
+
+    pub fn ascent(&self, style: FontStyle, size: u32) -> f32 {
+        let font = self.fonts.primary(style);
+        font.as_scaled(Self::scale(font, size)).ascent()
+    }
+
+    /// Returns the text drawn in the style. The image is `line_height` high,
+    /// unless fallback fonts used for the text reach further above or below
+    /// the baseline than the style's font.
+    pub fn get_text(&self, style: FontStyle, size: u32, color: Rgba<u8>, text: &str) -> Rc<RgbaImage> {
+        let key = (style, size, color.0, String::from(text));
+
+        if let Some(image) = self.cache.borrow().get(&key) {
+            return Rc::clone(image);
+        }
+
+        let image = Rc::new(self.render_text(style, size, color, text));
+        self.cache.borrow_mut().insert(key, Rc::clone(&image));
+
+        image
+    }
+
+    fn render_text(&self, style: FontStyle, size: u32, color: Rgba<u8>, text: &str) -> RgbaImage {
+        let runs = self.font_runs(style, text);
+        let primary = self.fonts.primary(style).as_scaled(Self::scale(self.fonts.primary(style), size));
+
+        // The line fits the highest ascent and the lowest descent of the fonts used.
+        let (ascent, descent) = runs.iter()
+            .map(|(font, _)| font.as_scaled(Self::scale(font, size)))
+            .fold((primary.ascent(), primary.descent()), |(ascent, descent), font|
+                (ascent.max(font.ascent()), descent.min(font.descent())));
+
+        let mut caret = 0.0f32;
+        let mut width = 0.0f32;
+        let mut positioned_runs = Vec::new();
+        for (font, run) in runs {
+            let scale = Self::scale(font, size);
+            let (advance, run_width) = Self::run_size(font, scale, run);
+
+            // All runs are aligned on a common baseline.
+            let y = (ascent - font.as_scaled(scale).ascent()).round() as i32;
+            positioned_runs.push((font, scale, caret.round() as i32, y, run));
+
+            width = width.max(caret + run_width);
+            caret += advance;
+        }
+
+        let width = (width.ceil() as u32).max(1);
+        let height = ((ascent - descent).ceil() as u32).max(1);
+
+        let mut buffer = RgbaImage::from_pixel(width, height, TRANSPARENT);
+        for (font, scale, x, y, run) in positioned_runs {
+            draw_text_mut(&mut buffer, color, x, y, scale, font, run);
+        }
+
+        buffer
+    }
+
+    // Splits the text into runs of characters drawn with the same font.
+    fn font_runs<'t>(&self, style: FontStyle, text: &'t str) -> Vec<(&'a FontArc, &'t str)> {
+        let mut runs: Vec<(&'a FontArc, &'t str)> = Vec::new();
+        let mut run_start = 0;
+
+        for (i, c) in text.char_indices() {
+            let font = self.fonts.font_for(style, c);
+            let end = i + c.len_utf8();
+
+            match runs.last_mut() {
+                Some((run_font, run)) if std::ptr::eq(*run_font, font) => *run = &text[run_start..end],
+                _ => {
+                    run_start = i;
+                    runs.push((font, &text[i..end]));
+                }
+            }
+        }
+
+        runs
+    }
+
+    // Returns the advance and the drawn width of the text. Unlike
+    // imageproc::drawing::text_size, the width includes the overhang
+    // of the last glyph past its advance, so nothing gets clipped.
+    fn run_size(font: &FontArc, scale: PxScale, text: &str) -> (f32, f32) {
+        let scaled_font = font.as_scaled(scale);
+        let mut caret = 0.0f32;
+        let mut width = 0.0f32;
+
+        for c in text.chars() {
+            let glyph_id = scaled_font.glyph_id(c);
+            let glyph = glyph_id.with_scale_and_position(scale, point(caret, scaled_font.ascent()));
+            caret += scaled_font.h_advance(glyph_id);
+
+            if let Some(outlined) = scaled_font.outline_glyph(glyph) {
+                width = width.max(outlined.px_bounds().max.x);
+            }
+            width = width.max(caret);
+        }
+
+        (caret, width)
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs;
+
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+
+/// Names accepted by `Theme::named`.
+pub const THEME_NAMES: [&str; 3] = ["dark", "light", "broadcast"];
+
+/// Colors shared by all panels. Driver colors come from the team colors,
+/// adjusted to stand out against `background`, with their alpha replaced by
+/// `line_alpha`.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub background: [u8; 4],
+    /// Text.
+    pub foreground: [u8; 4],
+    /// Helper lines inside plots, e.g. the zero line of the delta plot.
+    pub grid: [u8; 4],
+    /// Plot axes.
+    pub axis: [u8; 4],
+    /// Highlights: the title, the session header and the title and summary cards.
+    pub accent: [u8; 4],
+    pub line_alpha: u8
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            background: [15, 15, 15, 255],
+            foreground: [255, 255, 255, 255],
+            grid: [128, 128, 128, 255],
+            axis: [255, 255, 255, 255],
+            accent: [255, 255, 255, 255],
+            line_alpha: 180
+        }
+    }
+
+    pub fn light() -> Theme {
+        Theme {
+            background: [245, 245, 245, 255],
+            foreground: [25, 25, 25, 255],
+            grid: [200, 200, 200, 255],
+            axis: [70, 70, 70, 255],
+            accent: [0, 90, 200, 255],
+            line_alpha: 220
+        }
+    }
+
+    pub fn broadcast() -> Theme {
+        Theme {
+            background: [21, 21, 30, 255],
+            foreground: [240, 240, 240, 255],
+            grid: [70, 70, 85, 255],
+            axis: [170, 170, 185, 255],
+            accent: [225, 6, 0, 255],
+            line_alpha: 230
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "broadcast" => Some(Theme::broadcast()),
+            _ => None
+        }
+    }
+
+    /// Loads a theme from a JSON file. Omitted colors are taken from the dark theme.
+    pub fn from_file(path: &str) -> Result<Theme, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read theme file {}: {}", path, e))?;
+
+        serde_json::from_str(&json)
+            .map_err(|e| format!("Unable to parse theme file {}: {}", path, e))
+    }
+
+    /// Returns a named theme, or loads the theme file at the given path.
+    pub fn from_name_or_file(theme: &str) -> Result<Theme, String> {
+        match Theme::named(theme) {
+            Some(theme) => Ok(theme),
+            None => Theme::from_file(theme)
+        }
+    }
+
+    pub fn background(&self) -> Rgba<u8> {
+        Rgba(self.background)
+    }
+
+    pub fn foreground(&self) -> Rgba<u8> {
+        Rgba(self.foreground)
+    }
+
+    pub fn grid(&self) -> Rgba<u8> {
+        Rgba(self.grid)
+    }
+
+    pub fn axis(&self) -> Rgba<u8> {
+        Rgba(self.axis)
+    }
+
+    pub fn accent(&self) -> Rgba<u8> {
+        Rgba(self.accent)
+    }
+}
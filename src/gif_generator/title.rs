@@ -0,0 +1,25 @@
+use std::rc::Rc;
+
+use image::RgbaImage;
+
+use super::{Compositor, FontStyle, Rect, RenderConfig, TextCache};
+
+pub struct Title {
+    text: Rc<RgbaImage>
+}
+
+impl Title {
+    pub fn new(text: &str, text_cache: &TextCache, config: &RenderConfig) -> Title {
+        Title { text: text_cache.get_text(FontStyle::Bold, config.driver_font_size, 
+            config.theme.accent(), text) }
+    }
+
+    // Draws the title centered in the rect.
+    pub fn compose(&self, compositor: &mut Compositor, rect: Rect) {
+        let (x, y) = rect.position();
+        let dx = (rect.width as i64 - self.text.width() as i64) / 2;
+        let dy = (rect.height as i64 - self.text.height() as i64) / 2;
+
+        compositor.blend(&self.text, x + dx, y + dy);
+    }
+}
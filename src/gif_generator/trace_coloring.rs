@@ -0,0 +1,185 @@
+use std::rc::Rc;
+
+use image::{imageops, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::data_fetcher::{CompleteDriverData, DriverTelemetryData};
+
+use super::{FontStyle, RenderConfig, TextCache, Units, TRANSPARENT};
+
+// Color ramp from the lowest to the highest speed of the lap.
+const SPEED_RAMP: [[u8; 3]; 4] = [[40, 120, 255], [0, 210, 170], [255, 215, 0], [255, 45, 45]];
+
+const FULL_THROTTLE_COLOR: [u8; 3] = [0, 200, 80];
+const PARTIAL_THROTTLE_COLOR: [u8; 3] = [255, 200, 0];
+const BRAKE_COLOR: [u8; 3] = [255, 45, 45];
+
+// Throttle in percent from which a sample counts as full throttle.
+const FULL_THROTTLE: f32 = 98.0;
+
+pub const LEGEND_BAR_WIDTH: u32 = 100;
+pub const RAMP_STOPS: usize = SPEED_RAMP.len();
+
+/// What the drivers' traces on the track map are colored by.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceColoring {
+    /// Each driver's (team) color.
+    #[default]
+    Team,
+    /// A color ramp from the lowest to the highest speed of both laps.
+    Speed,
+    /// Full throttle, partial throttle or braking.
+    Pedals
+}
+
+/// Colors of the trace segments in the configured coloring.
+pub struct TraceColors {
+    coloring: TraceColoring,
+    speed_range: (i32, i32),
+    units: Units,
+    alpha: u8
+}
+
+/// Checks that the laps have the telemetry the configured coloring needs.
+pub fn check_trace_coloring(d1: &CompleteDriverData, d2: &CompleteDriverData, config: &RenderConfig) -> Result<(), String> {
+    if config.trace_coloring == TraceColoring::Pedals
+        && d1.telemetry.iter().chain(d2.telemetry.iter()).any(|t| t.throttle.is_none() || t.brake.is_none()) {
+        return Err(String::from("Coloring by pedals requires throttle and brake telemetry, fetch the laps again"));
+    }
+
+    Ok(())
+}
+
+impl TraceColors {
+    pub fn new(d1: &CompleteDriverData, d2: &CompleteDriverData, config: &RenderConfig) -> TraceColors {
+        let telemetry = || d1.telemetry.iter().chain(d2.telemetry.iter());
+        let min_speed = telemetry().map(|t| t.speed).min().unwrap_or(0);
+        let max_speed = telemetry().map(|t| t.speed).max().unwrap_or(0);
+
+        TraceColors { coloring: config.trace_coloring, speed_range: (min_speed, max_speed),
+            units: config.units, alpha: config.theme.line_alpha }
+    }
+
+    fn with_alpha(&self, [r, g, b]: [u8; 3]) -> Rgba<u8> {
+        Rgba([r, g, b, self.alpha])
+    }
+
+    fn speed_color(&self, speed: f32) -> Rgba<u8> {
+        let (min_speed, max_speed) = self.speed_range;
+        self.ramp_color((speed - min_speed as f32) / (max_speed - min_speed).max(1) as f32)
+    }
+
+    /// Color of the speed ramp at `ratio` between its lowest (0) and highest (1) speed.
+    pub fn ramp_color(&self, ratio: f32) -> Rgba<u8> {
+        let ratio = ratio.clamp(0.0, 1.0);
+        let position = ratio * (SPEED_RAMP.len() - 1) as f32;
+        let index = (position.floor() as usize).min(SPEED_RAMP.len() - 2);
+        let t = position - index as f32;
+        let (from, to) = (SPEED_RAMP[index], SPEED_RAMP[index + 1]);
+
+        self.with_alpha([0, 1, 2].map(|c| (from[c] as f32 + (to[c] as f32 - from[c] as f32) * t).round() as u8))
+    }
+
+    fn pedals_color(&self, t: &DriverTelemetryData) -> Rgba<u8> {
+        if t.brake == Some(true) {
+            self.with_alpha(BRAKE_COLOR)
+        } else if t.throttle.unwrap_or(0.0) >= FULL_THROTTLE {
+            self.with_alpha(FULL_THROTTLE_COLOR)
+        } else {
+            self.with_alpha(PARTIAL_THROTTLE_COLOR)
+        }
+    }
+
+    /// Color of the segment from the driver's sample `frame` to the next one.
+    pub fn segment_color(&self, driver_data: &CompleteDriverData, driver_color: Rgba<u8>, frame: usize) -> Rgba<u8> {
+        let (start, end) = (&driver_data.telemetry[frame], &driver_data.telemetry[frame + 1]);
+
+        match self.coloring {
+            TraceColoring::Team => driver_color,
+            TraceColoring::Speed => self.speed_color((start.speed + end.speed) as f32 / 2.0),
+            TraceColoring::Pedals => self.pedals_color(start)
+        }
+    }
+
+    /// What the traces are colored by.
+    pub fn coloring(&self) -> TraceColoring {
+        self.coloring
+    }
+
+    /// Labeled colors of the legend: the ends of the speed ramp, or the pedal states.
+    pub fn legend_entries(&self) -> Vec<(Rgba<u8>, String)> {
+        let (min_speed, max_speed) = self.speed_range;
+
+        match self.coloring {
+            TraceColoring::Team => Vec::new(),
+            TraceColoring::Speed => vec![
+                (self.speed_color(min_speed as f32), self.units.format_speed(min_speed)),
+                (self.speed_color(max_speed as f32), self.units.format_speed(max_speed))
+            ],
+            TraceColoring::Pedals => vec![
+                (self.with_alpha(FULL_THROTTLE_COLOR), String::from("Full throttle")),
+                (self.with_alpha(PARTIAL_THROTTLE_COLOR), String::from("Partial throttle")),
+                (self.with_alpha(BRAKE_COLOR), String::from("Braking"))
+            ]
+        }
+    }
+
+    /// Returns the legend shown in a corner of the track map, if the
+    /// traces are not colored by driver: the speed ramp between its lowest
+    /// and highest speed, or a swatch for every pedal state.
+    pub fn legend(&self, text_cache: &TextCache, config: &RenderConfig) -> Option<RgbaImage> {
+        let size = config.telemetry_label_font_size;
+        let height = text_cache.line_height(FontStyle::Regular, size);
+        let swatch_size = (height / 2).max(1);
+        let gap = config.telemetry_label_margin;
+        let label = |text: &str| 
+            LegendItem::Label(text_cache.get_text(FontStyle::Regular, size, config.theme.foreground(), text));
+
+        let entries = self.legend_entries();
+        let items = match self.coloring {
+            TraceColoring::Team => return None,
+            TraceColoring::Speed => vec![label(&entries[0].1), LegendItem::Ramp, label(&entries[1].1)],
+            TraceColoring::Pedals => entries.iter()
+                .flat_map(|(color, text)| [LegendItem::Swatch(*color), label(text)])
+                .collect()
+        };
+
+        let item_width = |item: &LegendItem| match item {
+            LegendItem::Label(text) => text.width(),
+            LegendItem::Ramp => LEGEND_BAR_WIDTH,
+            LegendItem::Swatch(_) => swatch_size
+        };
+        let width = items.iter().map(|item| item_width(item) + gap).sum::<u32>() - gap;
+        let mut legend = RgbaImage::from_pixel(width, height, TRANSPARENT);
+
+        let swatch_y = (height - swatch_size) / 2;
+        let fill_column = |legend: &mut RgbaImage, x: u32, Rgba([r, g, b, _]): Rgba<u8>|
+            for y in swatch_y..swatch_y + swatch_size {
+                legend.put_pixel(x, y, Rgba([r, g, b, 255]));
+            };
+
+        let mut x = 0;
+        for item in &items {
+            match item {
+                LegendItem::Label(text) => imageops::replace(&mut legend, text.as_ref(), x as i64, 0),
+                LegendItem::Ramp => for dx in 0..LEGEND_BAR_WIDTH {
+                    fill_column(&mut legend, x + dx, self.ramp_color(dx as f32 / (LEGEND_BAR_WIDTH - 1) as f32));
+                },
+                LegendItem::Swatch(color) => for dx in 0..swatch_size {
+                    fill_column(&mut legend, x + dx, *color);
+                }
+            }
+
+            x += item_width(item) + gap;
+        }
+
+        Some(legend)
+    }
+}
+
+enum LegendItem {
+    Label(Rc<RgbaImage>),
+    Ramp,
+    Swatch(Rgba<u8>)
+}
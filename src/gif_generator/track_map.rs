@@ -1,72 +1,96 @@
-use image::{imageops::overlay, Rgba, RgbaImage};
+use image::{Rgba, RgbaImage};
 
 use crate::data_fetcher::CompleteDriverData;
 
-use super::{draw_thick_line_mut, BACKGROUND_COLOR, THICKNESS, 
-    TRACK_HEIGHT, TRACK_WIDTH, TRANSPARENT};
+use super::{draw_antialiased_line_mut, draw_dashed_line_mut, driver2_dashes, trace_width, Compositor, RenderConfig, TextCache, TraceColors, TRANSPARENT};
 
 struct TrackMapDriverData<'a> {
     data: &'a CompleteDriverData,
     buffer: RgbaImage,
-    color: Rgba<u8>
+    color: Rgba<u8>,
+    dashes: Option<(f32, f32)>,
+    dash_phase: f32
 }
 
 pub struct TrackMap<'a> {
     d1: TrackMapDriverData<'a>,
     d2: TrackMapDriverData<'a>,
 
+    line_width: f32,
+    colors: TraceColors,
+    legend: Option<RgbaImage>,
+    padding: u32,
+    height: u32,
     current_frame: usize
 }
 
 impl <'a> TrackMap<'a> {
     pub fn new(d1_complete_data: &'a CompleteDriverData, d2_complete_data: &'a CompleteDriverData, 
-        d1_color: Rgba<u8>, d2_color: Rgba<u8>) -> TrackMap<'a> {
+        d1_color: Rgba<u8>, d2_color: Rgba<u8>, text_cache: &TextCache, config: &RenderConfig, 
+        (width, height): (u32, u32)) -> TrackMap<'a> {
+        let line_width = trace_width(config.thickness);
+
         let d1 = TrackMapDriverData {
             data: d1_complete_data, 
             color: d1_color, 
-            buffer: RgbaImage::from_pixel(TRACK_WIDTH, TRACK_HEIGHT, TRANSPARENT) 
+            buffer: RgbaImage::from_pixel(width, height, TRANSPARENT),
+            dashes: None,
+            dash_phase: 0.0
         };
 
         let d2= TrackMapDriverData {
             data: d2_complete_data, 
             color: d2_color, 
-            buffer: RgbaImage::from_pixel(TRACK_WIDTH, TRACK_HEIGHT, TRANSPARENT) 
+            buffer: RgbaImage::from_pixel(width, height, TRANSPARENT),
+            dashes: driver2_dashes(&d1_complete_data.driver, &d2_complete_data.driver, config, line_width),
+            dash_phase: 0.0
         };
 
-        TrackMap { d1, d2, current_frame: 0 }
+        let colors = TraceColors::new(d1_complete_data, d2_complete_data, config);
+        let legend = colors.legend(text_cache, config);
+
+        TrackMap { d1, d2, line_width, colors, legend, 
+            padding: config.padding, height, current_frame: 0 }
     }
 
     pub fn draw_next_frame(&mut self) {
         if Self::can_create_frame(&self.d1, self.current_frame) {
-            Self::draw_frame(&mut self.d1, self.current_frame);
+            Self::draw_frame(&mut self.d1, &self.colors, self.current_frame, self.line_width);
         }
     
         if Self::can_create_frame(&self.d2, self.current_frame) {
-            Self::draw_frame(&mut self.d2, self.current_frame);
+            Self::draw_frame(&mut self.d2, &self.colors, self.current_frame, self.line_width);
         }
 
         self.current_frame += 1;
     }
 
-    pub fn get_track_map(&self) -> RgbaImage {
-        let mut track_map = 
-            RgbaImage::from_pixel(TRACK_WIDTH, TRACK_HEIGHT, BACKGROUND_COLOR);
-        overlay(&mut track_map, &self.d1.buffer, 0, 0);
-        overlay(&mut track_map, &self.d2.buffer, 0, 0);
+    pub fn compose(&self, compositor: &mut Compositor, x: i64, y: i64) {
+        compositor.blend(&self.d1.buffer, x, y);
+        compositor.blend(&self.d2.buffer, x, y);
 
-        track_map
+        if let Some(legend) = &self.legend {
+            compositor.blend(legend, x + self.padding as i64, 
+                y + self.height as i64 - self.padding as i64 - legend.height() as i64);
+        }
     }
 
     fn can_create_frame(driver: &TrackMapDriverData, current_frame: usize) -> bool {
         current_frame + 1 < driver.data.telemetry.len()
     }
     
-    fn draw_frame(driver: &mut TrackMapDriverData, current_frame: usize) {
+    fn draw_frame(driver: &mut TrackMapDriverData, colors: &TraceColors, current_frame: usize, line_width: f32) {
         let p1 = 
-            (driver.data.telemetry[current_frame].y, driver.data.telemetry[current_frame].x);
+            (driver.data.telemetry[current_frame].y as f32, driver.data.telemetry[current_frame].x as f32);
         let p2 = 
-            (driver.data.telemetry[current_frame + 1].y, driver.data.telemetry[current_frame + 1].x);
+            (driver.data.telemetry[current_frame + 1].y as f32, driver.data.telemetry[current_frame + 1].x as f32);
 
-        draw_thick_line_mut(&mut driver.buffer, p1, p2, driver.color, THICKNESS);
+        let color = colors.segment_color(driver.data, driver.color, current_frame);
+
+        match driver.dashes {
+            Some(dashes) => driver.dash_phase = 
+                draw_dashed_line_mut(&mut driver.buffer, p1, p2, color, line_width, dashes, driver.dash_phase),
+            None => draw_antialiased_line_mut(&mut driver.buffer, p1, p2, color, line_width)
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,103 @@
+use image::{imageops, Rgba, RgbaImage};
+
+use crate::data_fetcher::LapData;
+
+use super::{contrast_with_background, draw_antialiased_line_mut, FontStyle, RenderConfig, TextCache, TRANSPARENT};
+
+/// Dry and wet tyre compounds, shown as a badge in the HUD.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TyreCompound {
+    Soft,
+    Medium,
+    Hard,
+    Intermediate,
+    Wet
+}
+
+impl TyreCompound {
+    /// Parses the compound names of FastF1. Unknown and test compounds have none.
+    pub fn named(name: &str) -> Option<TyreCompound> {
+        match name {
+            "SOFT" => Some(TyreCompound::Soft),
+            "MEDIUM" => Some(TyreCompound::Medium),
+            "HARD" => Some(TyreCompound::Hard),
+            "INTERMEDIATE" => Some(TyreCompound::Intermediate),
+            "WET" => Some(TyreCompound::Wet),
+            _ => None
+        }
+    }
+
+    pub fn letter(&self) -> &'static str {
+        match self {
+            TyreCompound::Soft => "S",
+            TyreCompound::Medium => "M",
+            TyreCompound::Hard => "H",
+            TyreCompound::Intermediate => "I",
+            TyreCompound::Wet => "W"
+        }
+    }
+
+    // Colors of the sidewall markings.
+    fn rgb(&self) -> [u8; 3] {
+        match self {
+            TyreCompound::Soft => [218, 41, 28],
+            TyreCompound::Medium => [255, 210, 0],
+            TyreCompound::Hard => [240, 240, 240],
+            TyreCompound::Intermediate => [67, 176, 42],
+            TyreCompound::Wet => [0, 103, 173]
+        }
+    }
+
+    /// Color of the badge, made to stand out against the theme's background,
+    /// like the drivers' colors.
+    pub fn color(&self, config: &RenderConfig) -> Rgba<u8> {
+        let background = [0, 1, 2].map(|c| config.theme.background()[c]);
+        let [r, g, b] = contrast_with_background(self.rgb(), background);
+        Rgba([r, g, b, 255])
+    }
+
+    /// Color of the letter on the badge: black on light, white on dark colors.
+    pub fn letter_color(&self, config: &RenderConfig) -> Rgba<u8> {
+        let Rgba([r, g, b, _]) = self.color(config);
+        let luma = 0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64;
+
+        if luma > 140.0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) }
+    }
+}
+
+/// Compound of the driver's lap, if it was fetched and is known.
+pub fn lap_compound(lap: &LapData) -> Option<TyreCompound> {
+    lap.compound.as_deref().and_then(TyreCompound::named)
+}
+
+/// Age of the driver's tyre, e.g. "5 laps", noting tyres fitted used.
+pub fn tyre_age_str(lap: &LapData) -> Option<String> {
+    let laps = lap.tyre_life?.round() as i64;
+    let age = if laps == 1 { String::from("1 lap") } else { format!("{} laps", laps) };
+
+    Some(match lap.fresh_tyre {
+        Some(false) => format!("{} (used)", age),
+        _ => age
+    })
+}
+
+/// Font size of the letter on a badge next to text of the given size.
+pub fn badge_letter_size(font_size: u32) -> u32 {
+    (font_size * 3 / 4).max(1)
+}
+
+/// Returns the compound badge, a circle in the compound's color with its
+/// letter, as high as a line of text of the given size.
+pub fn compound_badge(compound: TyreCompound, text_cache: &TextCache, config: &RenderConfig, font_size: u32) -> RgbaImage {
+    let size = text_cache.line_height(FontStyle::Regular, font_size);
+    let center = size as f32 / 2.0;
+    let mut badge = RgbaImage::from_pixel(size, size, TRANSPARENT);
+    draw_antialiased_line_mut(&mut badge, (center, center), (center, center), compound.color(config), size as f32 - 1.0);
+
+    let letter = text_cache.get_text(FontStyle::Bold, badge_letter_size(font_size),
+        compound.letter_color(config), compound.letter());
+    imageops::overlay(&mut badge, letter.as_ref(),
+        (size as i64 - letter.width() as i64) / 2, (size as i64 - letter.height() as i64) / 2);
+
+    badge
+}
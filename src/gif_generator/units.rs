@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+const MILES_PER_KILOMETRE: f64 = 0.621371;
+const FEET_PER_METRE: f64 = 3.28084;
+
+/// Units of the speeds and distances shown in the HUD, the plots and the cards.
+/// Telemetry is always in km/h and metres.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Units {
+    /// km/h and metres.
+    #[default]
+    Metric,
+    /// mph and feet.
+    Imperial
+}
+
+impl Units {
+    /// Parses `metric` or `imperial`.
+    pub fn named(name: &str) -> Option<Units> {
+        match name {
+            "metric" => Some(Units::Metric),
+            "imperial" => Some(Units::Imperial),
+            _ => None
+        }
+    }
+
+    /// Converts a speed in km/h.
+    pub fn speed(&self, kmh: f64) -> f64 {
+        match self {
+            Units::Metric => kmh,
+            Units::Imperial => kmh * MILES_PER_KILOMETRE
+        }
+    }
+
+    pub fn speed_unit(&self) -> &'static str {
+        match self {
+            Units::Metric => "km/h",
+            Units::Imperial => "mph"
+        }
+    }
+
+    /// Rounds a speed in km/h to a whole number in these units.
+    pub fn round_speed(&self, kmh: i32) -> i32 {
+        self.speed(kmh as f64).round() as i32
+    }
+
+    /// Formats a speed in km/h with its unit, e.g. "312 km/h".
+    pub fn format_speed(&self, kmh: i32) -> String {
+        format!("{} {}", self.round_speed(kmh), self.speed_unit())
+    }
+
+    /// Converts a distance in metres.
+    pub fn distance(&self, metres: f64) -> f64 {
+        match self {
+            Units::Metric => metres,
+            Units::Imperial => metres * FEET_PER_METRE
+        }
+    }
+
+    pub fn distance_unit(&self) -> &'static str {
+        match self {
+            Units::Metric => "m",
+            Units::Imperial => "ft"
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use image::{imageops, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use super::{Compositor, FontStyle, TextCache, TRANSPARENT};
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight
+}
+
+/// A logo and/or a line of text composited over every frame, after all panels.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatermarkConfig {
+    /// Path of a PNG logo.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Height the logo is scaled to, keeping its aspect ratio.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_height: Option<u32>,
+    /// Text drawn to the right of the logo.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub font_size: u32,
+    pub corner: Corner,
+    /// Distance from the edges of the frame, and between the logo and the text.
+    pub margin: u32,
+    /// Between 0 (invisible) and 1 (opaque).
+    pub opacity: f32
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        WatermarkConfig {
+            image: None,
+            image_height: None,
+            text: None,
+            font_size: 12,
+            corner: Corner::BottomRight,
+            margin: 10,
+            opacity: 0.8
+        }
+    }
+}
+
+impl WatermarkConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.image.is_none() && self.text.is_none() {
+            return Err(String::from("Watermark must have an image, a text or both"));
+        }
+
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(String::from("Watermark opacity must be between 0 and 1"));
+        }
+
+        if self.image_height == Some(0) {
+            return Err(String::from("Watermark image height must be positive"));
+        }
+
+        Ok(())
+    }
+}
+
+// The logo and the text are combined into a single layer with the
+// opacity already applied, so every frame takes only one blend.
+pub struct Watermark {
+    layer: RgbaImage,
+    corner: Corner,
+    margin: u32
+}
+
+impl Watermark {
+    pub fn new(config: &WatermarkConfig, text_cache: &TextCache, text_color: Rgba<u8>) -> Result<Watermark, String> {
+        let logo = match &config.image {
+            Some(path) => Some(Self::load_logo(path, config.image_height)?),
+            None => None
+        };
+        let text = config.text.as_ref()
+            .map(|text| text_cache.get_text(FontStyle::Regular, config.font_size, text_color, text));
+
+        let logo_width = logo.as_ref().map_or(0, |logo| logo.width());
+        let gap = if logo.is_some() && text.is_some() { config.margin } else { 0 };
+        let text_width = text.as_ref().map_or(0, |text| text.width());
+        let height = logo.as_ref().map_or(0, |logo| logo.height())
+            .max(text.as_ref().map_or(0, |text| text.height()));
+
+        let mut layer = RgbaImage::from_pixel((logo_width + gap + text_width).max(1), height.max(1), TRANSPARENT);
+        if let Some(logo) = &logo {
+            imageops::replace(&mut layer, logo, 0, ((height - logo.height()) / 2) as i64);
+        }
+        if let Some(text) = &text {
+            imageops::replace(&mut layer, text.as_ref(), (logo_width + gap) as i64, ((height - text.height()) / 2) as i64);
+        }
+
+        for pixel in layer.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * config.opacity).round() as u8;
+        }
+
+        Ok(Watermark { layer, corner: config.corner, margin: config.margin })
+    }
+
+    fn load_logo(path: &str, height: Option<u32>) -> Result<RgbaImage, String> {
+        let logo = image::open(path)
+            .map_err(|e| format!("Unable to load watermark image {}: {}", path, e))?
+            .to_rgba8();
+
+        Ok(match height {
+            Some(height) => {
+                let width = ((logo.width() as u64 * height as u64) / logo.height().max(1) as u64).max(1) as u32;
+                imageops::resize(&logo, width, height, imageops::FilterType::Lanczos3)
+            },
+            None => logo
+        })
+    }
+
+    pub fn compose(&self, compositor: &mut Compositor) {
+        let frame_width = compositor.frame().width() as i64;
+        let frame_height = compositor.frame().height() as i64;
+        let margin = self.margin as i64;
+
+        let x = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => margin,
+            Corner::TopRight | Corner::BottomRight => frame_width - margin - self.layer.width() as i64
+        };
+        let y = match self.corner {
+            Corner::TopLeft | Corner::TopRight => margin,
+            Corner::BottomLeft | Corner::BottomRight => frame_height - margin - self.layer.height() as i64
+        };
+
+        compositor.blend(&self.layer, x, y);
+    }
+}
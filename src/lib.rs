@@ -0,0 +1,51 @@
+//! Renders GIF comparisons between two drivers' best qualifying laps.
+//!
+//! Driver data can be obtained with [`fetch`] (which runs the fastf1-based
+//! script in `f1_fast/`) or constructed directly, and is then rendered with
+//! [`render`] or [`render_to_writer`]. [`render_summary`] and [`render_svg`]
+//! render only the final state as a still image.
+
+mod data_fetcher;
+mod gif_generator;
+
+use std::io::Write;
+
+use image::RgbaImage;
+
+pub use data_fetcher::{fetch, read_session_data, CompleteDriverData, CornerData, DriverData, DriverTelemetryData, LapData, 
+    SessionData, WeatherData};
+pub use gif_generator::{run_benchmark, Corner, FollowCameraConfig, FontConfig, Grid, GridCell, LapPortion, Layout, Orientation, PanelKind, PanelSpec, Rect, 
+    RenderConfig, RenderOptions, SlowMotion, Theme, TraceColoring, Units, WatermarkConfig, RESOLUTION_PRESETS, 
+    THEME_NAMES};
+
+/// Renders the comparison animation of both drivers to a GIF file at `output_path`.
+pub fn render(d1: CompleteDriverData, d2: CompleteDriverData, options: &RenderOptions, output_path: &str) -> Result<(), String> {
+    gif_generator::generate_gif(d1, d2, output_path, options)
+}
+
+/// Renders the final state of the comparison, with both full traces and the
+/// final lap times, to a PNG file at `output_path`.
+pub fn render_summary(d1: CompleteDriverData, d2: CompleteDriverData, options: &RenderOptions, output_path: &str) -> Result<(), String> {
+    gif_generator::generate_summary(d1, d2, output_path, options)
+}
+
+/// Renders the final state of the comparison into an image.
+pub fn render_summary_image(d1: CompleteDriverData, d2: CompleteDriverData, options: &RenderOptions) -> Result<RgbaImage, String> {
+    gif_generator::render_summary_image(d1, d2, options)
+}
+
+/// Renders the final state of the comparison as an SVG file at `output_path`,
+/// with the traces, axes and text as vector elements.
+pub fn render_svg(d1: CompleteDriverData, d2: CompleteDriverData, options: &RenderOptions, output_path: &str) -> Result<(), String> {
+    gif_generator::generate_svg(d1, d2, output_path, options)
+}
+
+/// Renders the final state of the comparison as an SVG document into `writer`.
+pub fn render_svg_to_writer<W: Write>(d1: CompleteDriverData, d2: CompleteDriverData, options: &RenderOptions, writer: W) -> Result<(), String> {
+    gif_generator::generate_svg_to_writer(d1, d2, writer, options)
+}
+
+/// Renders the comparison animation of both drivers as a GIF into `writer`.
+pub fn render_to_writer<W: Write>(d1: CompleteDriverData, d2: CompleteDriverData, options: &RenderOptions, writer: W) -> Result<(), String> {
+    gif_generator::generate_gif_to_writer(d1, d2, writer, options)
+}
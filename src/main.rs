@@ -1,29 +1,128 @@
-use data_fetcher::fetch;
-use gif_generator::generate_gif;
+use f1_gif_comparison::{fetch, read_session_data, render, render_summary, render_svg, run_benchmark, Layout, RenderConfig, RenderOptions, Theme, Units, 
+    RESOLUTION_PRESETS, THEME_NAMES};
 use std::env;
 use std::process;
 
-mod data_fetcher;
-mod gif_generator;
-
-fn usage_error(args: &Vec<String>) {
+fn usage_error(args: &[String]) -> ! {
     eprintln!(
-        "Usage: {} <framerate> <year> <country> <driver1> <driver2>",
+        "Usage: {} [--resolution <preset|WxH>] [--theme <name|theme.json>] [--config <config.json>] [--layout <layout.json>] [--speed <x>] [--fps <n>] [--units <metric|imperial>] [--summary <output.png> | --svg <output.svg>] <framerate> <year> <country> <driver1> <driver2>",
         args[0]
     );
+    eprintln!("       {} --benchmark [samples]", args[0]);
     eprintln!("Please use 3-letter abbrievation of the driver, e.g. HAM, VER, ...");
+    eprintln!("Resolution presets: {}", RESOLUTION_PRESETS.iter()
+        .map(|(name, (width, height))| format!("{} ({}x{})", name, width, height))
+        .collect::<Vec<_>>().join(", "));
+    eprintln!("Themes: {}", THEME_NAMES.join(", "));
     process::exit(1);
 }
 
+fn benchmark(args: &[String]) {
+    let no_samples = match args.get(2).map(|s| s.parse::<usize>()) {
+        None => 1500,
+        Some(Ok(no_samples)) if no_samples > 1 => no_samples,
+        _ => usage_error(args)
+    };
+
+    if let Err(e) = run_benchmark(no_samples, 20) {
+        eprintln!("Benchmark failed. Error: {}", e);
+        process::exit(3);
+    }
+}
+
+// What is rendered: the animation, or only the final state as a PNG or an SVG image.
+enum Output<'a> {
+    Gif,
+    Summary(&'a str),
+    Svg(&'a str)
+}
+
+fn config_error(e: String) -> ! {
+    eprintln!("{}", e);
+    process::exit(2);
+}
+
+// Splits the arguments into positional ones, the render config built
+// from --resolution (or the default one) with --config overrides and
+// --theme, --speed, --fps and --units applied, the layout given with --layout and
+// the output.
+fn parse_options(args: &[String]) -> (Vec<&str>, RenderConfig, Option<Layout>, Output<'_>) {
+    let mut positional = Vec::new();
+    let mut config_path = None;
+    let mut layout_path = None;
+    let mut resolution = None;
+    let mut theme = None;
+    let mut speed = None;
+    let mut fps = None;
+    let mut units = None;
+    let mut output = Output::Gif;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => config_path = Some(iter.next().unwrap_or_else(|| usage_error(args))),
+            "--layout" => layout_path = Some(iter.next().unwrap_or_else(|| usage_error(args))),
+            "--resolution" => resolution = Some(iter.next().unwrap_or_else(|| usage_error(args))),
+            "--theme" => theme = Some(iter.next().unwrap_or_else(|| usage_error(args))),
+            "--speed" => speed = Some(iter.next().and_then(|s| s.parse::<f32>().ok()).unwrap_or_else(|| usage_error(args))),
+            "--fps" => fps = Some(iter.next().and_then(|s| s.parse::<u32>().ok()).unwrap_or_else(|| usage_error(args))),
+            "--units" => units = Some(iter.next().and_then(|s| Units::named(s)).unwrap_or_else(|| usage_error(args))),
+            "--summary" => output = Output::Summary(iter.next().unwrap_or_else(|| usage_error(args))),
+            "--svg" => output = Output::Svg(iter.next().unwrap_or_else(|| usage_error(args))),
+            _ => positional.push(arg.as_str())
+        }
+    }
+
+    let mut config = match resolution {
+        Some(resolution) => {
+            let (width, height) = RenderConfig::parse_resolution(resolution)
+                .unwrap_or_else(|e| config_error(e));
+            RenderConfig::for_resolution(width, height)
+        },
+        None => RenderConfig::default()
+    };
+
+    if let Some(path) = config_path {
+        config = config.with_overrides_from_file(path).unwrap_or_else(|e| config_error(e));
+    }
+
+    if let Some(theme) = theme {
+        config.theme = Theme::from_name_or_file(theme).unwrap_or_else(|e| config_error(e));
+    }
+
+    if let Some(speed) = speed {
+        config.playback_speed = speed;
+    }
+
+    if fps.is_some() {
+        config.output_framerate = fps;
+    }
+
+    if let Some(units) = units {
+        config.units = units;
+    }
+
+    let layout = layout_path.map(|path| Layout::from_file(path).unwrap_or_else(|e| config_error(e)));
+
+    (positional, config, layout, output)
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() != 6 {
+    if args.get(1).is_some_and(|arg| arg == "--benchmark") {
+        benchmark(&args);
+        return;
+    }
+
+    let (positional, config, layout, output) = parse_options(&args);
+
+    if positional.len() != 5 {
         usage_error(&args);
     }
 
-    let framerate = args[1].parse::<u32>();
-    let year = args[2].parse::<u32>();
+    let framerate = positional[0].parse::<u32>();
+    let year = positional[1].parse::<u32>();
     if framerate.is_err() || year.is_err() {
         usage_error(&args);
     }
@@ -34,12 +133,26 @@ fn main() {
     match fetch(
         framerate,
         year,
-        args[3].as_str(),
-        args[4].as_str(),
-        args[5].as_str(),
+        positional[2],
+        positional[3],
+        positional[4],
         false,
     ) {
-        Ok((d1, d2)) => generate_gif(d1, d2, "animation.gif", framerate),
+        Ok((d1, d2)) => {
+            let options = RenderOptions { framerate, print_progress: true, config, layout,
+                session: read_session_data().ok() };
+
+            let (result, output) = match output {
+                Output::Gif => (render(d1, d2, &options, "animation.gif"), "a gif"),
+                Output::Summary(path) => (render_summary(d1, d2, &options, path), "a summary image"),
+                Output::Svg(path) => (render_svg(d1, d2, &options, path), "an SVG image")
+            };
+
+            if let Err(e) = result {
+                eprintln!("Unable to create {}. Error: {}", output, e);
+                process::exit(3);
+            }
+        },
 
         Err(e) => {
             eprintln!("Unable to create a gif. Error: {}", e);